@@ -0,0 +1,268 @@
+use crate::facts::Facts;
+use crate::rules::Comparison;
+use crate::template;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const ADVISORIES_FILE: &str = "advisories.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Condition {
+    pub fact: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+}
+
+// A recommendation given when all of its conditions hold, e.g.
+// Advisory(category: "Umbrella", message: "Take an umbrella", when: [(fact: "chance_of_rain", comparison: Above, threshold: 50.0)], hours: Some(12))
+//
+// Like rules, `hours` looks at the next hours of the forecast instead and `at` at the
+// next time each of those hours of the day comes round. The message is a template
+// filled in from the hour that matched. Only the first match in each category is given,
+// so later advisories in a category act as fallbacks and one with no conditions always
+// applies.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Advisory {
+    pub category: String,
+    pub message: String,
+    #[serde(default)]
+    pub when: Vec<Condition>,
+    #[serde(default)]
+    pub hours: Option<usize>,
+    #[serde(default)]
+    pub at: Vec<u32>,
+}
+
+// What advisories.ron holds. Leaving `advisories` out keeps the built in ones.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AdvisoryConfiguration {
+    #[serde(default = "default_advisories")]
+    pub advisories: Vec<Advisory>,
+    // Length of the window best_window looks for
+    #[serde(default = "default_window_hours")]
+    pub window_hours: usize,
+}
+
+impl Default for AdvisoryConfiguration {
+    fn default() -> Self {
+        Self {
+            advisories: default_advisories(),
+            window_hours: default_window_hours(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Advice {
+    pub category: String,
+    pub message: String,
+}
+
+fn default_window_hours() -> usize {
+    2
+}
+
+fn advisory(category: &str, message: &str, when: &[(&str, Comparison, f64)]) -> Advisory {
+    Advisory {
+        category: category.to_string(),
+        message: message.to_string(),
+        when: when
+            .iter()
+            .map(|(fact, comparison, threshold)| Condition {
+                fact: fact.to_string(),
+                comparison: *comparison,
+                threshold: *threshold,
+            })
+            .collect(),
+        hours: None,
+        at: Vec::new(),
+    }
+}
+
+fn default_advisories() -> Vec<Advisory> {
+    use Comparison::*;
+    vec![
+        advisory("Wear", "A heavy winter coat, hat and gloves, it feels like {feelslike:.0}{temp_unit}", &[("feelslike_c", Below, -10.0)]),
+        advisory("Wear", "A winter coat, hat and gloves, it feels like {feelslike:.0}{temp_unit}", &[("feelslike_c", Below, 0.0)]),
+        advisory("Wear", "A warm jacket, it feels like {feelslike:.0}{temp_unit}", &[("feelslike_c", Below, 10.0)]),
+        advisory("Wear", "A light jacket or jumper, it feels like {feelslike:.0}{temp_unit}", &[("feelslike_c", Below, 16.0)]),
+        advisory("Wear", "A t-shirt with a layer for later, it feels like {feelslike:.0}{temp_unit}", &[("feelslike_c", Below, 24.0)]),
+        advisory("Wear", "Light clothes and sunscreen, it feels like {feelslike:.0}{temp_unit}", &[]),
+        Advisory {
+            hours: Some(12),
+            ..advisory("Umbrella", "Take an umbrella, {chance_of_rain}% chance of rain at {time_of_day}", &[("chance_of_rain", Above, 50.0)])
+        },
+        advisory("Frost", "Frost risk tonight, cover or bring in tender plants", &[("forecast_mintemp_c", Below, 2.0)]),
+        Advisory {
+            hours: Some(24),
+            ..advisory("Frost", "Frost risk at {time_of_day}, cover or bring in tender plants", &[("temp_c", Below, 1.0)])
+        },
+        Advisory {
+            at: vec![8, 17],
+            ..advisory("Cycling", "Rain likely for the {time_of_day} commute, {chance_of_rain}% chance", &[("chance_of_rain", Above, 50.0)])
+        },
+        Advisory {
+            at: vec![8, 17],
+            ..advisory("Cycling", "Strong gusts for the {time_of_day} commute, up to {gust:.0} {speed_unit}", &[("gust_kph", Above, 45.0)])
+        },
+        Advisory {
+            at: vec![8, 17],
+            ..advisory("Cycling", "Good cycling weather for the {time_of_day} commute", &[("cycling_score", Above, 60.0)])
+        },
+        advisory("Hydration", "Heat index {heatindex:.0}{temp_unit}, drink water regularly and rest in the shade", &[("heatindex_c", Above, 32.0)]),
+        Advisory {
+            hours: Some(12),
+            ..advisory("Hydration", "Heat index reaches {heatindex:.0}{temp_unit} at {time_of_day}, carry water", &[("heatindex_c", Above, 32.0)])
+        },
+        advisory("Outdoors", "Best time to be outside today is {best_window}", &[("best_window_score", Above, 50.0)]),
+        advisory("Outdoors", "No good time to be outside today, {best_window} is the least bad", &[("best_window_score", Above, 0.0)]),
+    ]
+}
+
+// 0 to 100, how pleasant an hour is to be out in
+fn outdoor_score(hour: &Facts) -> Option<f64> {
+    let feels = hour.number("feelslike_c")?;
+    let precipitation = hour
+        .number("chance_of_rain")
+        .unwrap_or(0.0)
+        .max(hour.number("chance_of_snow").unwrap_or(0.0));
+    let wind = hour.number("wind_kph").unwrap_or(0.0);
+    let uv = hour.number("uv").unwrap_or(0.0);
+    let score = 100.0
+        - ((feels - 21.0).abs() * 4.0).min(60.0)
+        - (precipitation * 0.6)
+        - (wind - 15.0).max(0.0) * 1.5
+        - (uv - 6.0).max(0.0) * 5.0;
+    Some(score.clamp(0.0, 100.0))
+}
+
+// Like outdoor_score, but rain, wind and the dark matter more on a bike
+fn cycling_score(hour: &Facts) -> Option<f64> {
+    let feels = hour.number("feelslike_c")?;
+    let precipitation = hour
+        .number("chance_of_rain")
+        .unwrap_or(0.0)
+        .max(hour.number("chance_of_snow").unwrap_or(0.0));
+    let wind = hour.number("wind_kph").unwrap_or(0.0);
+    let gust = hour.number("gust_kph").unwrap_or(wind);
+    let dark = hour.number("is_day") == Some(0.0);
+    let score = 100.0
+        - ((feels - 15.0).abs() * 2.5).min(50.0)
+        - precipitation * 0.8
+        - (wind - 10.0).max(0.0) * 2.0
+        - if gust > 40.0 { 30.0 } else { 0.0 }
+        - if dark { 10.0 } else { 0.0 };
+    Some(score.clamp(0.0, 100.0))
+}
+
+fn hour_of_day(hour: &Facts) -> Option<u32> {
+    match hour.get("time") {
+        Some(time) => time.to_string().get(11..13)?.parse().ok(),
+        None => None,
+    }
+}
+
+fn day(hour: &Facts) -> Option<String> {
+    hour.get("time")?.to_string().get(..10).map(String::from)
+}
+
+// outdoor_score, cycling_score and time_of_day ("13:00") for each hour, and the best daylight window of
+// `window_hours` left today as best_window ("13:00-15:00") and best_window_score
+pub fn add_facts(facts: &mut Facts, window_hours: usize) {
+    for hour in facts.hours.iter_mut() {
+        if let Some(time) = hour.get("time").and_then(|time| time.to_string().get(11..).map(String::from)) {
+            hour.insert_text("time_of_day", &time);
+        }
+        if let Some(score) = outdoor_score(hour) {
+            hour.insert_number("outdoor_score", score);
+        }
+        if let Some(score) = cycling_score(hour) {
+            hour.insert_number("cycling_score", score);
+        }
+    }
+
+    let today = facts.hours.first().and_then(day);
+    let daylight: Vec<&Facts> = facts
+        .hours
+        .iter()
+        .take_while(|hour| day(hour) == today)
+        .collect();
+    let best = daylight
+        .windows(window_hours.max(1))
+        .filter(|window| window.iter().all(|hour| hour.number("is_day") != Some(0.0)))
+        .filter_map(|window| {
+            let scores: Option<Vec<f64>> = window.iter().map(|hour| hour.number("outdoor_score")).collect();
+            let scores = scores?;
+            Some((window, scores.iter().sum::<f64>() / scores.len() as f64))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((window, score)) = best {
+        let start = hour_of_day(window[0]).unwrap_or(0);
+        let end = start + window.len() as u32;
+        let window = format!("{start:02}:00-{:02}:00", end % 24);
+        facts.insert_text("best_window", &window);
+        facts.insert_number("best_window_score", score);
+    }
+}
+
+impl Condition {
+    fn holds(&self, facts: &Facts) -> bool {
+        let Some(value) = facts.number(&self.fact) else {
+            return false;
+        };
+        self.comparison.holds(value, self.threshold)
+    }
+}
+
+impl Advisory {
+    // The facts the advisory applies to, the first hour that meets it if it looks ahead
+    fn matching<'a>(&self, facts: &'a Facts) -> Option<&'a Facts> {
+        let holds = |facts: &Facts| self.when.iter().all(|condition| condition.holds(facts));
+        if !self.at.is_empty() {
+            // Only the next 24 hours, so each hour of the day comes round once
+            return facts
+                .hours
+                .iter()
+                .take(24)
+                .filter(|hour| hour_of_day(hour).is_some_and(|hour| self.at.contains(&hour)))
+                .find(|hour| holds(hour));
+        }
+        match self.hours {
+            Some(hours) => facts.hours.iter().take(hours).find(|hour| holds(hour)),
+            None => holds(facts).then_some(facts),
+        }
+    }
+}
+
+pub fn advise(config: &AdvisoryConfiguration, facts: &mut Facts) -> Vec<Advice> {
+    add_facts(facts, config.window_hours);
+    let mut advice: Vec<Advice> = Vec::new();
+    for advisory in &config.advisories {
+        if advice.iter().any(|given| given.category == advisory.category) {
+            continue;
+        }
+        if let Some(matched) = advisory.matching(facts) {
+            // Hours only know about themselves, fall back to the overall facts for the rest
+            let mut merged = facts.clone();
+            merged.extend(matched);
+            advice.push(Advice {
+                category: advisory.category.clone(),
+                message: template::render(&advisory.message, &merged),
+            });
+        }
+    }
+    advice
+}
+
+pub fn load(working_directory: &Path) -> AdvisoryConfiguration {
+    let Ok(data) = std::fs::read_to_string(working_directory.join(ADVISORIES_FILE)) else {
+        return AdvisoryConfiguration::default();
+    };
+    match ron::from_str(&data) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to parse {ADVISORIES_FILE}: {e:?}");
+            AdvisoryConfiguration::default()
+        }
+    }
+}
@@ -1,15 +1,41 @@
+use crate::geolocate::{self, AutoLocation};
+use chrono::NaiveDate;
 use json::JsonValue;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, time::Instant};
+use std::{
+    fmt::Display,
+    net::IpAddr,
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+pub const PROVIDER: &str = "weatherapi";
+
+// Anything left out of api_config.ron keeps its value from the layers under it, see config::resolve
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
 pub struct ApiRequestConfiguration {
     pub q: Location,
     pub days: Option<usize>,
-    //pub dt: Option<chrono::NaiveDate>,
+    // Only meaningful for a single run, it is checked against today's date
+    #[serde(skip)]
+    pub dt: Option<DateRange>,
     pub hour: Option<bool>,
     // pub lang: Option<String>,
     pub requests: RequestTypes,
+    pub auto_location: AutoLocation,
+}
+
+impl ApiRequestConfiguration {
+    // With Auto looked up, so every request goes to the same place. make_request
+    // doesn't do this itself as the daemon already has its own fix.
+    pub fn located(&self) -> Self {
+        Self {
+            q: geolocate::locate(&self.q, &self.auto_location),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -26,9 +52,97 @@ pub enum Location {
     SearchID(usize),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub enum ApiError {
-    InvalidApiKey,
+// Limits WeatherAPI puts on dt and end_dt
+const HISTORY_START: (i32, u32, u32) = (2010, 1, 1);
+const FORECAST_DAYS: i64 = 14;
+const FUTURE_DAYS: i64 = 300;
+const MAX_RANGE_DAYS: i64 = 30;
+
+// Which endpoint serves a date range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateEndpoint {
+    History,
+    Forecast,
+    Future,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub endpoint: DateEndpoint,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DateError {
+    Reversed(NaiveDate, NaiveDate),
+    TooLong(i64),
+    BeforeHistory(NaiveDate),
+    TooFarAhead(NaiveDate),
+    SpansEndpoints,
+}
+
+impl Display for DateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (year, month, day) = HISTORY_START;
+        match self {
+            Self::Reversed(from, to) => write!(f, "{to} is before {from}"),
+            Self::TooLong(days) => write!(
+                f,
+                "range covers {days} days, at most {MAX_RANGE_DAYS} can be requested at once"
+            ),
+            Self::BeforeHistory(date) => write!(
+                f,
+                "{date} is before {year:04}-{month:02}-{day:02}, the earliest date with history"
+            ),
+            Self::TooFarAhead(date) => write!(
+                f,
+                "{date} is more than {FUTURE_DAYS} days ahead, the furthest the future forecast goes"
+            ),
+            Self::SpansEndpoints => write!(
+                f,
+                "range mixes past, forecast ({FORECAST_DAYS} days ahead) and future dates, split it up"
+            ),
+        }
+    }
+}
+
+impl DateRange {
+    pub fn new(from: NaiveDate, to: NaiveDate, today: NaiveDate) -> Result<Self, DateError> {
+        if to < from {
+            return Err(DateError::Reversed(from, to));
+        }
+        let days = (to - from).num_days() + 1;
+        if days > MAX_RANGE_DAYS {
+            return Err(DateError::TooLong(days));
+        }
+        let (year, month, day) = HISTORY_START;
+        if NaiveDate::from_ymd_opt(year, month, day).is_some_and(|start| from < start) {
+            return Err(DateError::BeforeHistory(from));
+        }
+        if (to - today).num_days() > FUTURE_DAYS {
+            return Err(DateError::TooFarAhead(to));
+        }
+
+        let endpoint = |date: NaiveDate| match (date - today).num_days() {
+            ..0 => DateEndpoint::History,
+            0..FORECAST_DAYS => DateEndpoint::Forecast,
+            _ => DateEndpoint::Future,
+        };
+        if endpoint(from) != endpoint(to) {
+            return Err(DateError::SpansEndpoints);
+        }
+        Ok(Self {
+            from,
+            to,
+            endpoint: endpoint(from),
+        })
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let to = self.to;
+        self.from.iter_days().take_while(move |date| *date <= to)
+    }
 }
 
 impl Display for Location {
@@ -36,7 +150,7 @@ impl Display for Location {
         match self {
             Self::Coordinate(lat, lon) => write!(f, "{lat},{lon}"),
             Self::City(name) => write!(f, "{name}"),
-            Self::USZip(zip) => write!(f, "{zip}"),
+            Self::USZip(zip) => write!(f, "{zip:05}"),
             Self::Post(code) => write!(f, "{code}"),
             Self::Metar(code) => write!(f, "metar:{code}"),
             Self::Iata(code) => write!(f, "iata:{code}"),
@@ -47,18 +161,147 @@ impl Display for Location {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum LocationError {
+    Empty,
+    Latitude(f64),
+    Longitude(f64),
+    Zip(String),
+    Metar(String),
+    Iata(String),
+    SearchID(String),
+}
+
+impl Display for LocationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "location is empty"),
+            Self::Latitude(lat) => write!(f, "latitude {lat} is outside -90 to 90"),
+            Self::Longitude(lon) => write!(f, "longitude {lon} is outside -180 to 180"),
+            Self::Zip(zip) => write!(f, "{zip} is not a US zip code, those have 5 digits or are ZIP+4 like 12345-6789"),
+            Self::Metar(code) => write!(f, "{code} is not a METAR station, those are 4 letter ICAO codes like EGLL"),
+            Self::Iata(code) => write!(f, "{code} is not an airport, those are 3 letter IATA codes like DXB"),
+            Self::SearchID(id) => write!(f, "{id} is not a search id, those are numbers from the search command"),
+        }
+    }
+}
+
+// UK postcodes like "SW1A 1AA" and Canadian ones like "K1A 0B1"
+fn is_postcode(text: &str) -> bool {
+    let code: Vec<char> = text.chars().filter(|c| *c != ' ').collect();
+    if text.matches(' ').count() > 1 || !code.iter().all(char::is_ascii_alphanumeric) {
+        return false;
+    }
+    let canadian = code.len() == 6
+        && code
+            .iter()
+            .enumerate()
+            .all(|(i, c)| if i % 2 == 0 { c.is_ascii_alphabetic() } else { c.is_ascii_digit() });
+    let british = (5..=7).contains(&code.len()) && {
+        let (outward, inward) = code.split_at(code.len() - 3);
+        outward[0].is_ascii_alphabetic()
+            && outward.iter().any(char::is_ascii_digit)
+            && inward[0].is_ascii_digit()
+            && inward[1..].iter().all(char::is_ascii_alphabetic)
+    };
+    canadian || british
+}
+
+// The inverse of Display, so anything printed or saved can be given back to --location
+impl FromStr for Location {
+    type Err = LocationError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let prefixed = |prefix: &str| {
+            text.get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .map(|_| text[prefix.len()..].trim())
+        };
+
+        if text.is_empty() {
+            return Err(LocationError::Empty);
+        }
+        if text.eq_ignore_ascii_case("auto:ip") {
+            return Ok(Self::Auto);
+        }
+        if let Some(code) = prefixed("metar:") {
+            if code.len() != 4 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(LocationError::Metar(code.to_string()));
+            }
+            return Ok(Self::Metar(code.to_ascii_uppercase()));
+        }
+        if let Some(code) = prefixed("iata:") {
+            if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(LocationError::Iata(code.to_string()));
+            }
+            return Ok(Self::Iata(code.to_ascii_uppercase()));
+        }
+        if let Some(id) = prefixed("id:") {
+            return id
+                .parse()
+                .map(Self::SearchID)
+                .map_err(|_| LocationError::SearchID(id.to_string()));
+        }
+        if let Some((lat, lon)) = text.split_once(',') {
+            if let (Ok(lat), Ok(lon)) = (lat.trim().parse::<f64>(), lon.trim().parse::<f64>()) {
+                if !(-90.0..=90.0).contains(&lat) {
+                    return Err(LocationError::Latitude(lat));
+                }
+                if !(-180.0..=180.0).contains(&lon) {
+                    return Err(LocationError::Longitude(lon));
+                }
+                return Ok(Self::Coordinate(lat, lon));
+            }
+        }
+        if text.parse::<IpAddr>().is_ok() {
+            return Ok(Self::IP(text.to_string()));
+        }
+        if text.chars().all(|c| c.is_ascii_digit() || c == '-') && text.starts_with(|c: char| c.is_ascii_digit()) {
+            // ZIP+4 is narrower than the weather needs, the first five digits are kept
+            let zip = text
+                .split_once('-')
+                .filter(|(_, extension)| extension.len() == 4 && extension.chars().all(|c| c.is_ascii_digit()))
+                .map_or(text, |(zip, _)| zip);
+            return match zip.parse() {
+                Ok(number) if zip.len() == 5 => Ok(Self::USZip(number)),
+                _ => Err(LocationError::Zip(text.to_string())),
+            };
+        }
+        if is_postcode(text) {
+            return Ok(Self::Post(text.to_ascii_uppercase()));
+        }
+        Ok(Self::City(text.to_string()))
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ApiResponse<'a> {
     pub current: Option<Result<&'a JsonValue, ApiResponseError<'a>>>,
     pub alerts: Option<Result<&'a JsonValue, ApiResponseError<'a>>>,
     pub forecast: Option<Result<&'a JsonValue, ApiResponseError<'a>>>,
+    pub dated: Option<Result<&'a JsonValue, ApiResponseError<'a>>>,
+    pub marine: Option<Result<&'a JsonValue, ApiResponseError<'a>>>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct RequestTypes {
     pub current: bool,
     pub forecast: bool,
     pub alerts: bool,
+    pub marine: bool,
+}
+
+impl Default for RequestTypes {
+    fn default() -> Self {
+        Self {
+            current: true,
+            forecast: true,
+            alerts: false,
+            marine: false,
+        }
+    }
 }
 
 pub struct Api {
@@ -66,6 +309,8 @@ pub struct Api {
     cache_current: Option<(JsonValue, Instant)>,
     cache_alerts: Option<(JsonValue, Instant)>,
     cache_forecast: Option<(JsonValue, Instant)>,
+    cache_dated: Option<(JsonValue, Instant)>,
+    cache_marine: Option<(JsonValue, Instant)>,
 }
 
 impl Api {
@@ -75,9 +320,16 @@ impl Api {
             cache_current: None,
             cache_alerts: None,
             cache_forecast: None,
+            cache_dated: None,
+            cache_marine: None,
         }
     }
 
+    // A second client for the same account, with nothing cached
+    pub fn with_key(&self) -> Self {
+        Self::new(self.key.clone())
+    }
+
     pub fn get_cached_alerts(&self) -> Option<&(JsonValue, Instant)> {
         self.cache_alerts.as_ref()
     }
@@ -90,7 +342,57 @@ impl Api {
         self.cache_forecast.as_ref()
     }
 
-    pub fn make_request<'a>(&'a mut self, config: &ApiRequestConfiguration) -> ApiResponse {
+    pub fn get_cached_dated(&self) -> Option<&(JsonValue, Instant)> {
+        self.cache_dated.as_ref()
+    }
+
+    pub fn get_cached_marine(&self) -> Option<&(JsonValue, Instant)> {
+        self.cache_marine.as_ref()
+    }
+
+    // The daemon writes its latest responses to the working directory so other
+    // commands can show them without touching the network
+    pub fn save_cache(&self, working_directory: &Path) -> std::io::Result<()> {
+        let caches = [
+            ("cache_current.json", &self.cache_current),
+            ("cache_alerts.json", &self.cache_alerts),
+            ("cache_forecast.json", &self.cache_forecast),
+            ("cache_marine.json", &self.cache_marine),
+        ];
+        for (file, cache) in caches {
+            let Some((response, timestamp)) = cache else {
+                continue;
+            };
+            let fetched = SystemTime::now()
+                .checked_sub(timestamp.elapsed())
+                .unwrap_or(UNIX_EPOCH)
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            let document = json::object! {
+                fetched: fetched.as_secs(),
+                response: response.clone(),
+            };
+            std::fs::write(working_directory.join(file), document.dump())?;
+        }
+        Ok(())
+    }
+
+    pub fn load_cache(&mut self, working_directory: &Path) {
+        let load = |file: &str| -> Option<(JsonValue, Instant)> {
+            let text = std::fs::read_to_string(working_directory.join(file)).ok()?;
+            let mut document = json::parse(&text).ok()?;
+            let fetched = UNIX_EPOCH + Duration::new(document["fetched"].as_u64()?, 0);
+            let age = SystemTime::now().duration_since(fetched).unwrap_or_default();
+            let timestamp = Instant::now().checked_sub(age).unwrap_or(Instant::now());
+            Some((document["response"].take(), timestamp))
+        };
+        self.cache_current = load("cache_current.json");
+        self.cache_alerts = load("cache_alerts.json");
+        self.cache_forecast = load("cache_forecast.json");
+        self.cache_marine = load("cache_marine.json");
+    }
+
+    pub fn make_request(&mut self, config: &ApiRequestConfiguration) -> ApiResponse<'_> {
         /*
          * Solution bypasses safety checks unnecessarily,
          * but I'm too lazy to get it working in a better
@@ -101,50 +403,31 @@ impl Api {
         let requests = &config.requests;
         ApiResponse {
             current: requests.current.then(|| {
-                match unsafe { &mut *ptr }.make_current_request(config) {
-                    Ok(json) => {
-                        if json["error"].is_null() {
-                            Ok(json)
-                        } else {
-                            Err(ApiResponseError::ApiError(json))
-                        }
-                    }
-                    Err(req_err) => Err(ApiResponseError::RequestError(req_err)),
-                }
+                unsafe { &mut *ptr }.make_current_request(config).and_then(checked)
             }),
 
             alerts: requests.alerts.then(|| {
-                match unsafe { &mut *ptr }.make_alerts_request(config) {
-                    Ok(json) => {
-                        if json["error"].is_null() {
-                            Ok(json)
-                        } else {
-                            Err(ApiResponseError::ApiError(json))
-                        }
-                    }
-                    Err(req_err) => Err(ApiResponseError::RequestError(req_err)),
-                }
+                unsafe { &mut *ptr }.make_alerts_request(config).and_then(checked)
             }),
 
             forecast: requests.forecast.then(|| {
-                match unsafe { &mut *ptr }.make_forecast_request(config) {
-                    Ok(json) => {
-                        if json["error"].is_null() {
-                            Ok(json)
-                        } else {
-                            Err(ApiResponseError::ApiError(json))
-                        }
-                    }
-                    Err(req_err) => Err(ApiResponseError::RequestError(req_err)),
-                }
-            })
+                unsafe { &mut *ptr }.make_forecast_request(config).and_then(checked)
+            }),
+
+            marine: requests.marine.then(|| {
+                unsafe { &mut *ptr }.make_marine_request(config).and_then(checked)
+            }),
+
+            dated: config.dt.map(|range| {
+                unsafe { &mut *ptr }.make_dated_request(config, &range).and_then(checked)
+            }),
         }
     }
 
     fn make_alerts_request(
         &mut self,
         config: &ApiRequestConfiguration,
-    ) -> Result<&JsonValue, reqwest::Error> {
+    ) -> Result<&JsonValue, ApiResponseError<'_>> {
         let ApiRequestConfiguration { q, .. } = config;
 
         let request = reqwest::blocking::Client::new()
@@ -152,9 +435,9 @@ impl Api {
             .header("key", self.key.clone())
             .query(&[("q", format!("{q}"))]);
 
-        let response = request.send().unwrap();
+        let response = request.send()?;
 
-        let response = json::parse(response.text().unwrap().as_ref()).unwrap();
+        let response = parse(response)?;
 
         self.cache_alerts = Some((response.clone(), Instant::now()));
 
@@ -164,53 +447,189 @@ impl Api {
     fn make_current_request(
         &mut self,
         config: &ApiRequestConfiguration,
-    ) -> Result<&JsonValue, reqwest::Error> {
+    ) -> Result<&JsonValue, ApiResponseError<'_>> {
+        let ApiRequestConfiguration { q, .. } = config;
+
+        let request = reqwest::blocking::Client::new()
+            .post("http://api.weatherapi.com/v1/current.json")
+            .header("key", self.key.clone())
+            .query(&[("q", format!("{q}"))])
+            .query(&[("aqi", "yes"), ("pollen", "yes")]);
+
+        let response = request.send()?;
+
+        let response = parse(response)?;
+
+        self.cache_current = Some((response.clone(), Instant::now()));
+
+        Ok(&self.cache_current.as_ref().unwrap().0)
+    }
+
+    fn make_forecast_request(
+        &mut self,
+        config: &ApiRequestConfiguration,
+    ) -> Result<&JsonValue, ApiResponseError<'_>> {
         let ApiRequestConfiguration { q, days, hour, .. } = config;
 
         let mut request = reqwest::blocking::Client::new()
-            .post("http://api.weatherapi.com/v1/current.json")
+            .post("http://api.weatherapi.com/v1/forecast.json")
             .header("key", self.key.clone())
-            .query(&[("q", format!("{q}"))]);
+            .query(&[("q", format!("{q}"))])
+            .query(&[("aqi", "yes"), ("pollen", "yes")]);
 
         if let Some(days) = days {
             request = request.query(&[("days", days)])
         }
 
-        if let Some(hour) = hour {
-            request = request.query(&[("hour", hour)])
-        }
+        let response = request.send()?;
 
-        let response = request.send().unwrap();
+        let mut response = parse(response)?;
 
-        let response = json::parse(response.text().unwrap().as_ref()).unwrap();
+        // WeatherAPI always sends the hourly breakdown, which is most of the response.
+        // Drop it when it was turned off so the cache stays small.
+        if *hour == Some(false) {
+            for forecastday in response["forecast"]["forecastday"].members_mut() {
+                forecastday.remove("hour");
+            }
+        }
 
-        self.cache_current = Some((response.clone(), Instant::now()));
+        self.cache_forecast = Some((response.clone(), Instant::now()));
 
-        Ok(&self.cache_current.as_ref().unwrap().0)
+        Ok(&self.cache_forecast.as_ref().unwrap().0)
     }
 
-    fn make_forecast_request(
+    fn make_marine_request(
         &mut self,
         config: &ApiRequestConfiguration,
-    ) -> Result<&JsonValue, reqwest::Error> {
+    ) -> Result<&JsonValue, ApiResponseError<'_>> {
         let ApiRequestConfiguration { q, days, .. } = config;
 
         let mut request = reqwest::blocking::Client::new()
-            .post("http://api.weatherapi.com/v1/forecast.json")
+            .post("http://api.weatherapi.com/v1/marine.json")
             .header("key", self.key.clone())
-            .query(&[("q", format!("{q}"))]);
+            .query(&[("q", format!("{q}"))])
+            .query(&[("tides", "yes")]);
 
         if let Some(days) = days {
             request = request.query(&[("days", days)])
         }
 
-        let response = request.send().unwrap();
+        let response = request.send()?;
 
-        let response = json::parse(response.text().unwrap().as_ref()).unwrap();
+        let response = parse(response)?;
 
-        self.cache_forecast = Some((response.clone(), Instant::now()));
+        self.cache_marine = Some((response.clone(), Instant::now()));
 
-        Ok(&self.cache_forecast.as_ref().unwrap().0)
+        Ok(&self.cache_marine.as_ref().unwrap().0)
+    }
+
+    // Places matching a name, postcode or coordinate, for picking a SearchID
+    pub fn search(&self, text: &str) -> Result<JsonValue, ApiResponseError<'static>> {
+        let response = reqwest::blocking::Client::new()
+            .post("http://api.weatherapi.com/v1/search.json")
+            .header("key", self.key.clone())
+            .query(&[("q", text)])
+            .send()?;
+        parse(response)
+    }
+
+    // Sun and moon times, on their own since they aren't cached or used by the daemon
+    pub fn astronomy(&self, q: &Location, date: NaiveDate) -> Result<JsonValue, ApiResponseError<'static>> {
+        let response = reqwest::blocking::Client::new()
+            .post("http://api.weatherapi.com/v1/astronomy.json")
+            .header("key", self.key.clone())
+            .query(&[("q", format!("{q}")), ("dt", date.to_string())])
+            .send()?;
+        parse(response)
+    }
+
+    // Past dates come from history.json, which takes a range. Forecast dates come from
+    // forecast.json trimmed to the range, and future.json only takes one date at a time.
+    fn make_dated_request(
+        &mut self,
+        config: &ApiRequestConfiguration,
+        range: &DateRange,
+    ) -> Result<&JsonValue, ApiResponseError<'_>> {
+        let ApiRequestConfiguration { q, hour, .. } = config;
+        let today = chrono::Local::now().date_naive();
+
+        let get = |endpoint: &str, query: &[(&str, String)]| -> Result<JsonValue, ApiResponseError<'static>> {
+            let response = reqwest::blocking::Client::new()
+                .post(format!("http://api.weatherapi.com/v1/{endpoint}"))
+                .header("key", self.key.clone())
+                .query(&[("q", format!("{q}"))])
+                .query(&[("aqi", "yes")])
+                .query(query)
+                .send()?;
+            parse(response)
+        };
+
+        let mut response = match range.endpoint {
+            DateEndpoint::History => {
+                let mut query = vec![("dt", range.from.to_string())];
+                if range.to != range.from {
+                    query.push(("end_dt", range.to.to_string()));
+                }
+                get("history.json", &query)?
+            }
+            DateEndpoint::Forecast => {
+                let days = (range.to - today).num_days() + 1;
+                let mut response = get("forecast.json", &[("days", days.to_string())])?;
+                let forecastdays = response["forecast"]["forecastday"].take();
+                response["forecast"]["forecastday"] = forecastdays
+                    .members()
+                    .filter(|forecastday| {
+                        forecastday["date"]
+                            .as_str()
+                            .and_then(|date| date.parse::<NaiveDate>().ok())
+                            .is_some_and(|date| date >= range.from && date <= range.to)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .into();
+                response
+            }
+            DateEndpoint::Future => {
+                let mut merged = get("future.json", &[("dt", range.from.to_string())])?;
+                for date in range.dates().skip(1) {
+                    if !merged["error"].is_null() {
+                        break;
+                    }
+                    let mut response = get("future.json", &[("dt", date.to_string())])?;
+                    if !response["error"].is_null() {
+                        merged = response;
+                        break;
+                    }
+                    for forecastday in response["forecast"]["forecastday"].members_mut() {
+                        let _ = merged["forecast"]["forecastday"].push(forecastday.take());
+                    }
+                }
+                merged
+            }
+        };
+
+        if *hour == Some(false) {
+            for forecastday in response["forecast"]["forecastday"].members_mut() {
+                forecastday.remove("hour");
+            }
+        }
+
+        self.cache_dated = Some((response.clone(), Instant::now()));
+
+        Ok(&self.cache_dated.as_ref().unwrap().0)
+    }
+}
+
+// Proxies and outages can answer with an HTML page instead of JSON
+fn parse(response: reqwest::blocking::Response) -> Result<JsonValue, ApiResponseError<'static>> {
+    Ok(json::parse(&response.text()?)?)
+}
+
+fn checked(json: &JsonValue) -> Result<&JsonValue, ApiResponseError<'_>> {
+    if json["error"].is_null() {
+        Ok(json)
+    } else {
+        Err(ApiResponseError::ApiError(json))
     }
 }
 
@@ -218,4 +637,59 @@ impl Api {
 pub enum ApiResponseError<'a> {
     RequestError(reqwest::Error),
     ApiError(&'a JsonValue),
+    InvalidResponse(json::Error),
+}
+
+impl From<reqwest::Error> for ApiResponseError<'_> {
+    fn from(e: reqwest::Error) -> Self {
+        Self::RequestError(e)
+    }
+}
+
+impl From<json::Error> for ApiResponseError<'_> {
+    fn from(e: json::Error) -> Self {
+        Self::InvalidResponse(e)
+    }
+}
+
+impl ApiResponseError<'_> {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RequestError(_) => "request",
+            Self::ApiError(_) => "api",
+            Self::InvalidResponse(_) => "response",
+        }
+    }
+
+    // Process exit code for scripts, clap already uses 1 and 2
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::RequestError(_) => 3,
+            Self::ApiError(_) => 4,
+            Self::InvalidResponse(_) => 5,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::RequestError(e) => e.to_string(),
+            Self::ApiError(json) => json["error"]["message"].to_string(),
+            Self::InvalidResponse(e) => format!("the provider did not answer with JSON: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zip_codes() {
+        assert!(matches!("90210".parse(), Ok(Location::USZip(90210))));
+        assert!(matches!("02134-1234".parse(), Ok(Location::USZip(2134))));
+        assert_eq!("02134-1234".parse::<Location>().unwrap().to_string(), "02134");
+        for text in ["9021", "902101", "90210-123", "90210-12345", "90210-1234-5678", "90210--1234"] {
+            assert!(matches!(text.parse::<Location>(), Err(LocationError::Zip(_))), "{text}");
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use json::JsonValue;
+use serde::Serialize;
+
+// Sun elevations in degrees that each event is defined by. Sunrise and sunset allow
+// for refraction and the size of the sun's disc.
+const SUNRISE: f64 = -0.833;
+const CIVIL_TWILIGHT: f64 = -6.0;
+const NAUTICAL_TWILIGHT: f64 = -12.0;
+const GOLDEN_HOUR: f64 = 6.0;
+
+const SYNODIC_MONTH: f64 = 29.530588853;
+// 2000-01-06 18:14 UTC
+const KNOWN_NEW_MOON: i64 = 947182440;
+
+const MOON_PHASES: [&str; 8] = [
+    "New Moon",
+    "Waxing Crescent",
+    "First Quarter",
+    "Waxing Gibbous",
+    "Full Moon",
+    "Waning Gibbous",
+    "Last Quarter",
+    "Waning Crescent",
+];
+
+// Times are local to the location, as HH:MM
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Astronomy {
+    pub date: String,
+    pub source: &'static str,
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+    pub moonrise: Option<String>,
+    pub moonset: Option<String>,
+    pub moon_phase: Option<String>,
+    pub moon_illumination: Option<f64>,
+    pub civil_dawn: Option<String>,
+    pub civil_dusk: Option<String>,
+    pub nautical_dawn: Option<String>,
+    pub nautical_dusk: Option<String>,
+    pub golden_hour_morning_end: Option<String>,
+    pub golden_hour_evening_start: Option<String>,
+}
+
+fn julian_to_utc(julian: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(((julian - 2440587.5) * 86400.0).round() as i64, 0)
+}
+
+// When the sun passes `elevation` on `date`, rising and then setting. None when it
+// stays above or below it all day, as happens near the poles.
+pub fn sun_crossings(date: NaiveDate, lat: f64, lon: f64, elevation: f64) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let days = (date - NaiveDate::from_ymd_opt(2000, 1, 1)?).num_days() as f64 - lon / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * days).rem_euclid(360.0).to_radians();
+    let center = 1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = 2451545.0 + days + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * longitude).sin();
+    let declination = (longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+
+    let lat = lat.to_radians();
+    let cos_hour_angle = (elevation.to_radians().sin() - lat.sin() * declination.sin())
+        / (lat.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+    Some((julian_to_utc(transit - hour_angle)?, julian_to_utc(transit + hour_angle)?))
+}
+
+// Days since the last new moon
+pub fn moon_age(at: DateTime<Utc>) -> f64 {
+    ((at.timestamp() - KNOWN_NEW_MOON) as f64 / 86400.0).rem_euclid(SYNODIC_MONTH)
+}
+
+pub fn moon_phase(age: f64) -> &'static str {
+    MOON_PHASES[((age / SYNODIC_MONTH * 8.0).round() as usize) % 8]
+}
+
+pub fn moon_illumination(age: f64) -> f64 {
+    ((1.0 - (age / SYNODIC_MONTH * std::f64::consts::TAU).cos()) / 2.0 * 100.0).round()
+}
+
+// WeatherAPI gives the local time and its epoch, the difference is the UTC offset
+pub fn utc_offset(response: &JsonValue) -> Option<FixedOffset> {
+    let epoch = response["location"]["localtime_epoch"].as_i64()?;
+    let local = NaiveDateTime::parse_from_str(response["location"]["localtime"].as_str()?, "%Y-%m-%d %H:%M").ok()?;
+    let utc = DateTime::from_timestamp(epoch, 0)?.naive_utc();
+    // Round to the quarter hour, localtime has no seconds
+    let seconds = ((local - utc).num_seconds() as f64 / 900.0).round() as i32 * 900;
+    FixedOffset::east_opt(seconds)
+}
+
+// Mean solar time at the longitude, for places the provider hasn't given an offset for.
+// Civil time is usually within an hour of it.
+pub fn solar_offset(lon: f64) -> FixedOffset {
+    let hours = (lon / 15.0).round().clamp(-12.0, 12.0) as i32;
+    FixedOffset::east_opt(hours * 3600).unwrap()
+}
+
+fn local_time(time: DateTime<Utc>, offset: FixedOffset) -> String {
+    time.with_timezone(&offset).format("%H:%M").to_string()
+}
+
+// WeatherAPI writes "07:45 AM", and "No moonrise" on days without one
+fn provider_time(value: &JsonValue) -> Option<String> {
+    let time = NaiveTime::parse_from_str(value.as_str()?, "%I:%M %p").ok()?;
+    Some(time.format("%H:%M").to_string())
+}
+
+impl Astronomy {
+    // Everything but moonrise and moonset, which need a much longer lunar model
+    pub fn calculate(date: NaiveDate, lat: f64, lon: f64, offset: FixedOffset) -> Self {
+        let crossings = |elevation| sun_crossings(date, lat, lon, elevation);
+        let rising = |elevation| crossings(elevation).map(|(rise, _)| local_time(rise, offset));
+        let setting = |elevation| crossings(elevation).map(|(_, set)| local_time(set, offset));
+        let noon = date
+            .and_hms_opt(12, 0, 0)
+            .map(|noon| noon.and_utc() - offset)
+            .unwrap_or_default();
+        let age = moon_age(noon);
+        Self {
+            date: date.to_string(),
+            source: "calculated",
+            sunrise: rising(SUNRISE),
+            sunset: setting(SUNRISE),
+            moonrise: None,
+            moonset: None,
+            moon_phase: Some(String::from(moon_phase(age))),
+            moon_illumination: Some(moon_illumination(age)),
+            civil_dawn: rising(CIVIL_TWILIGHT),
+            civil_dusk: setting(CIVIL_TWILIGHT),
+            nautical_dawn: rising(NAUTICAL_TWILIGHT),
+            nautical_dusk: setting(NAUTICAL_TWILIGHT),
+            golden_hour_morning_end: rising(GOLDEN_HOUR),
+            golden_hour_evening_start: setting(GOLDEN_HOUR),
+        }
+    }
+
+    // The provider's sun and moon times, with the twilight and golden hour the
+    // provider doesn't have calculated for the location it resolved
+    pub fn from_json(response: &JsonValue) -> Option<Self> {
+        let location = &response["location"];
+        let (lat, lon) = (location["lat"].as_f64()?, location["lon"].as_f64()?);
+        let offset = utc_offset(response).unwrap_or(*chrono::Local::now().offset());
+        let date = location["localtime"]
+            .as_str()
+            .and_then(|time| time.get(..10))
+            .and_then(|date| date.parse().ok())
+            .unwrap_or_else(|| Utc::now().with_timezone(&offset).date_naive());
+        let astro = &response["astronomy"]["astro"];
+        Some(Self {
+            source: crate::api::PROVIDER,
+            sunrise: provider_time(&astro["sunrise"]),
+            sunset: provider_time(&astro["sunset"]),
+            moonrise: provider_time(&astro["moonrise"]),
+            moonset: provider_time(&astro["moonset"]),
+            moon_phase: astro["moon_phase"].as_str().map(String::from),
+            moon_illumination: astro["moon_illumination"].as_f64(),
+            ..Self::calculate(date, lat, lon, offset)
+        })
+    }
+}
+
+// Minutes from `now` until the sun next passes `elevation`, rising or setting. Looks at
+// tomorrow once today's has gone by, so a rule on it stops matching after the event.
+pub fn minutes_until(now: DateTime<Utc>, lat: f64, lon: f64, offset: FixedOffset, elevation: f64, rising: bool) -> Option<f64> {
+    let today = now.with_timezone(&offset).date_naive();
+    [today, today.succ_opt()?]
+        .into_iter()
+        .filter_map(|date| sun_crossings(date, lat, lon, elevation))
+        .map(|(rise, set)| if rising { rise } else { set })
+        .find(|time| *time > now)
+        .map(|time| (time - now).num_seconds() as f64 / 60.0)
+}
+
+// Named sun events for rules, e.g. minutes_until_sunset Below 30
+pub const EVENTS: [(&str, f64, bool); 8] = [
+    ("sunrise", SUNRISE, true),
+    ("sunset", SUNRISE, false),
+    ("civil_dawn", CIVIL_TWILIGHT, true),
+    ("civil_dusk", CIVIL_TWILIGHT, false),
+    ("nautical_dawn", NAUTICAL_TWILIGHT, true),
+    ("nautical_dusk", NAUTICAL_TWILIGHT, false),
+    ("golden_hour_morning_end", GOLDEN_HOUR, true),
+    ("golden_hour_evening_start", GOLDEN_HOUR, false),
+];
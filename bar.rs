@@ -0,0 +1,109 @@
+use crate::facts::Facts;
+use crate::template;
+use clap::ValueEnum;
+use std::time::Duration;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BarMode {
+    Waybar,
+    Polybar,
+    I3blocks,
+    Tmux,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarOptions {
+    pub mode: BarMode,
+    pub template: Option<String>,
+    pub tooltip: Option<String>,
+    pub short: Option<String>,
+    pub max_age: Duration,
+}
+
+// temp, wind and the other unitless facts are in the units the user picked
+const DEFAULT_TEMPLATE: &str = "{temp:.0}{temp_unit} {condition}";
+const DEFAULT_TOOLTIP: &str = "{location}: {condition}\nFeels like {feelslike:.0}{temp_unit}\nHumidity {humidity}%\nWind {wind:.0} {speed_unit} {wind_dir}\nToday {forecast_mintemp:.0}{temp_unit} to {forecast_maxtemp:.0}{temp_unit}, {forecast_daily_chance_of_rain}% chance of rain";
+const DEFAULT_SHORT: &str = "{temp:.0}{temp_unit}";
+
+// Waybar styles modules by class, the other bars get the matching color
+fn class(facts: &Facts, max_age: Duration) -> &'static str {
+    if facts.number("alert_count").is_some_and(|count| count > 0.0) {
+        return "alert";
+    }
+    if facts
+        .number("age_minutes")
+        .is_none_or(|age| age * 60.0 > max_age.as_secs() as f64)
+    {
+        return "stale";
+    }
+    match facts.number("temp_c") {
+        Some(temp) if temp < 0.0 => "cold",
+        Some(temp) if temp < 10.0 => "cool",
+        Some(temp) if temp < 20.0 => "mild",
+        Some(temp) if temp < 30.0 => "warm",
+        Some(_) => "hot",
+        None => "stale",
+    }
+}
+
+fn color(class: &str) -> &'static str {
+    match class {
+        "alert" => "#ff5555",
+        "cold" => "#5e81ac",
+        "cool" => "#88c0d0",
+        "mild" => "#a3be8c",
+        "warm" => "#ebcb8b",
+        "hot" => "#bf616a",
+        _ => "#808080",
+    }
+}
+
+// Temperature mapped from -20°C..40°C onto 0..100, for waybar's format-icons
+fn percentage(facts: &Facts) -> u8 {
+    let temp = facts.number("temp_c").unwrap_or(0.0);
+    ((temp + 20.0) / 60.0 * 100.0).clamp(0.0, 100.0).round() as u8
+}
+
+// Bars run this every few seconds, so it only ever reads the daemon's cache
+pub fn print(facts: &Facts, options: &BarOptions) {
+    let available = facts.get("temp_c").is_some();
+    let render = |template: &Option<String>, default: &str| {
+        if available {
+            template::render(template.as_deref().unwrap_or(default), facts)
+        } else {
+            String::from("N/A")
+        }
+    };
+    let text = render(&options.template, DEFAULT_TEMPLATE);
+    let short = render(&options.short, DEFAULT_SHORT);
+    let class = if available {
+        class(facts, options.max_age)
+    } else {
+        "unavailable"
+    };
+
+    match options.mode {
+        BarMode::Waybar => {
+            let tooltip = if available {
+                render(&options.tooltip, DEFAULT_TOOLTIP)
+            } else {
+                String::from("No cached weather, is the daemon running?")
+            };
+            let output = json::object! {
+                text: text,
+                tooltip: tooltip,
+                class: class,
+                percentage: percentage(facts),
+            };
+            println!("{}", output.dump());
+        }
+        BarMode::Polybar => println!("%{{F{}}}{}%{{F-}}", color(class), text),
+        // full_text, short_text and color, one per line
+        BarMode::I3blocks => {
+            println!("{text}");
+            println!("{short}");
+            println!("{}", color(class));
+        }
+        BarMode::Tmux => println!("{short}"),
+    }
+}
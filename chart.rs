@@ -0,0 +1,261 @@
+use crate::models::{ForecastDay, Hour};
+use crate::units::{DisplayUnits, Unit};
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const ASCII_SPARKS: [char; 8] = ['_', '.', '-', '~', '=', '+', '*', '#'];
+
+// Width to draw charts at, falling back to 80 columns when not on a terminal
+pub fn terminal_width() -> usize {
+    match ratatui::crossterm::terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(80),
+    }
+}
+
+fn bounds(values: &[f64]) -> (f64, f64) {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    // A flat series still gets some height, relative to its size so large values count too
+    if max - min <= f64::EPSILON * max.abs().max(1.0) {
+        (min - 1.0, max + 1.0)
+    } else {
+        (min, max)
+    }
+}
+
+// Picks `width` evenly spaced samples so a series of any length fills the chart
+fn resample(values: &[f64], width: usize) -> Vec<f64> {
+    if values.is_empty() || width == 0 {
+        return Vec::new();
+    }
+    (0..width)
+        .map(|i| {
+            let position = i as f64 * (values.len() - 1) as f64 / (width - 1).max(1) as f64;
+            let (low, fraction) = (position.floor() as usize, position.fract());
+            let high = (low + 1).min(values.len() - 1);
+            values[low] + (values[high] - values[low]) * fraction
+        })
+        .collect()
+}
+
+pub fn sparkline(values: &[f64], ascii: bool) -> String {
+    let symbols = if ascii { ASCII_SPARKS } else { SPARKS };
+    let (min, max) = bounds(values);
+    values
+        .iter()
+        .map(|value| {
+            let level = ((value - min) / (max - min) * 7.0).round() as usize;
+            symbols[level.min(7)]
+        })
+        .collect()
+}
+
+// Wind direction is where the wind comes from, the arrow shows where it is blowing
+pub fn wind_arrow(degree: f64, ascii: bool) -> char {
+    let arrows = if ascii {
+        ['v', '/', '<', '\\', '^', '/', '>', '\\']
+    } else {
+        ['↓', '↙', '←', '↖', '↑', '↗', '→', '↘']
+    };
+    arrows[((degree.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+// A line plot `height` rows tall with the y axis labelled on the left. Unicode mode
+// draws with braille dots, which gives 2x4 points per character cell.
+pub fn line_plot(values: &[f64], width: usize, height: usize, ascii: bool) -> Vec<String> {
+    let (min, max) = bounds(values);
+    let label_width = format!("{max:.0}").len().max(format!("{min:.0}").len()) + 1;
+    let plot_width = width.saturating_sub(label_width + 1).max(2);
+    let (x_scale, y_scale) = if ascii { (1, 1) } else { (2, 4) };
+    let dots_wide = plot_width * x_scale;
+    let dots_high = height * y_scale;
+
+    let points: Vec<usize> = resample(values, dots_wide)
+        .iter()
+        .map(|value| (((value - min) / (max - min)) * (dots_high - 1) as f64).round() as usize)
+        .collect();
+
+    let mut grid = vec![vec![false; dots_wide]; dots_high];
+    for (x, &y) in points.iter().enumerate() {
+        // Fill between neighbours so steep changes stay connected
+        let previous = if x > 0 { points[x - 1] } else { y };
+        let (low, high) = (y.min(previous), y.max(previous));
+        for row in grid.iter_mut().take(high + 1).skip(low) {
+            row[x] = true;
+        }
+    }
+
+    (0..height)
+        .map(|row| {
+            let label = match row {
+                0 => format!("{max:.0}"),
+                r if r == height - 1 => format!("{min:.0}"),
+                _ => String::new(),
+            };
+            let cells: String = (0..plot_width)
+                .map(|column| {
+                    if ascii {
+                        return if grid[dots_high - 1 - row][column] { '*' } else { ' ' };
+                    }
+                    // Braille dot numbering, left column 1,2,3,7 and right column 4,5,6,8
+                    const BITS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+                    let mut bits = 0;
+                    for (dx, column_bits) in BITS.iter().enumerate() {
+                        for (dy, bit) in column_bits.iter().enumerate() {
+                            let y = dots_high - 1 - (row * 4 + dy);
+                            if grid[y][column * 2 + dx] {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap_or(' ')
+                })
+                .collect();
+            format!("{label:>label_width$}{}{cells}", if ascii { '|' } else { '│' })
+        })
+        .collect()
+}
+
+// Vertical bars for percentages, one column per value
+pub fn percent_bars(values: &[f64], height: usize, ascii: bool) -> Vec<String> {
+    let eighths = height * 8;
+    (0..height)
+        .map(|row| {
+            let floor = (height - 1 - row) * 8;
+            let label = match row {
+                0 => "100%",
+                r if r == height - 1 => "  0%",
+                _ => "    ",
+            };
+            let bars: String = values
+                .iter()
+                .map(|value| {
+                    let filled = (value.clamp(0.0, 100.0) / 100.0 * eighths as f64).round() as usize;
+                    match filled.saturating_sub(floor) {
+                        0 => ' ',
+                        n if n >= 8 => if ascii { '#' } else { '█' },
+                        n => if ascii { '.' } else { SPARKS[n - 1] },
+                    }
+                })
+                .collect();
+            format!("{label}{}{bars}", if ascii { '|' } else { '│' })
+        })
+        .collect()
+}
+
+pub fn print_hourly(hours: &[Hour], ascii: bool, units: &DisplayUnits) {
+    let width = terminal_width();
+    let hours: Vec<&Hour> = hours.iter().take(width.saturating_sub(6)).collect();
+    if hours.is_empty() {
+        println!("No hourly forecast available\n");
+        return;
+    }
+    // Hour of day every 6 columns
+    let mut axis = String::new();
+    for (i, hour) in hours.iter().enumerate() {
+        if axis.chars().count() > i {
+            continue;
+        }
+        match hour.time.as_ref().and_then(|t| t.get(11..13)) {
+            Some(label) if i % 6 == 0 => axis.push_str(label),
+            _ => axis.push(' '),
+        }
+    }
+
+    let (temperature, speed) = (units.temperature(), units.speed());
+    let temps: Vec<f64> = hours
+        .iter()
+        .filter_map(|h| h.temp_c)
+        .map(|temp| temperature.in_unit(temp))
+        .collect();
+    println!("{:=^32}", format!("Hourly Temperature {}", temperature.symbol()));
+    for line in line_plot(&temps, width.min(100), 8, ascii) {
+        println!("{line}");
+    }
+    println!("Trend: {}\n", sparkline(&temps, ascii));
+
+    let chances: Vec<f64> = hours
+        .iter()
+        .map(|h| h.chance_of_rain.unwrap_or(0.0).max(h.chance_of_snow.unwrap_or(0.0)))
+        .collect();
+    println!("{:=^32}", "Chance of Precipitation");
+    for line in percent_bars(&chances, 5, ascii) {
+        println!("{line}");
+    }
+    if ascii {
+        println!("    +{}", "-".repeat(hours.len()));
+    } else {
+        println!("    └{}", "─".repeat(hours.len()));
+    }
+    println!("     {axis}\n");
+
+    let speeds: Vec<f64> = hours
+        .iter()
+        .map(|h| speed.in_unit(h.wind_kph.unwrap_or(0.0)))
+        .collect();
+    println!("{:=^32}", "Wind");
+    println!(
+        "Dir  {}",
+        hours
+            .iter()
+            .map(|h| h.wind_degree.map_or(' ', |d| wind_arrow(d, ascii)))
+            .collect::<String>()
+    );
+    println!("{:<5}{}", speed.symbol(), sparkline(&speeds, ascii));
+    println!(
+        "     {:.0}-{:.0} {}\n",
+        speeds.iter().cloned().fold(f64::INFINITY, f64::min),
+        speeds.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        speed.symbol()
+    );
+}
+
+pub fn print_daily(days: &[ForecastDay], ascii: bool, units: &DisplayUnits) {
+    let temperature = units.temperature();
+    let highs: Vec<f64> = days
+        .iter()
+        .filter_map(|d| d.maxtemp_c)
+        .map(|temp| temperature.in_unit(temp))
+        .collect();
+    let lows: Vec<f64> = days
+        .iter()
+        .filter_map(|d| d.mintemp_c)
+        .map(|temp| temperature.in_unit(temp))
+        .collect();
+    if highs.is_empty() {
+        return;
+    }
+    println!("{:=^32}", "Daily Outlook");
+    let symbol = temperature.symbol();
+    println!("Highs {symbol}: {} {:.0}..{:.0}", sparkline(&highs, ascii), bounds(&highs).0, bounds(&highs).1);
+    println!("Lows  {symbol}: {} {:.0}..{:.0}", sparkline(&lows, ascii), bounds(&lows).0, bounds(&lows).1);
+    for day in days {
+        let chance = day
+            .daily_chance_of_rain
+            .unwrap_or(0.0)
+            .max(day.daily_chance_of_snow.unwrap_or(0.0));
+        let bar_width = (chance / 100.0 * 20.0).round() as usize;
+        println!(
+            "{}  {:>3.0}% {}",
+            day.date.as_deref().unwrap_or("-"),
+            chance,
+            if ascii { "#" } else { "█" }.repeat(bar_width)
+        );
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_series_get_some_height() {
+        assert_eq!(bounds(&[5.0, 5.0]), (4.0, 6.0));
+        assert_eq!(bounds(&[1013.25, 1013.25 + 1e-13]).0, 1012.25);
+        assert_eq!(bounds(&[0.0, 2.0]), (0.0, 2.0));
+    }
+}
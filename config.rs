@@ -0,0 +1,485 @@
+use crate::api::{ApiRequestConfiguration, Location, RequestTypes};
+use crate::daemon::{default_working_directory, DaemonConfiguration};
+use crate::utils::Schedule;
+use ron::extensions::Extensions;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+pub const API_CONFIG_FILE: &str = "api_config.ron";
+pub const DAEMON_CONFIG_FILE: &str = "daemon_config.ron";
+
+// Environment variables are this followed by the setting's name in capitals, with
+// dots as underscores, e.g. WEATHD_DAYS=5 or WEATHD_REQUESTS_ALERTS=true
+const ENVIRONMENT_PREFIX: &str = "WEATHD_";
+
+const DEFAULT_DAYS: usize = 3;
+
+// Every setting in api_config.ron and daemon_config.ron, in the order `config show` lists them
+const API_SETTINGS: [&str; 8] = [
+    "q",
+    "days",
+    "hour",
+    "requests.current",
+    "requests.forecast",
+    "requests.alerts",
+    "requests.marine",
+    "auto_location",
+];
+const DAEMON_SETTINGS: [&str; 9] = [
+    "working_directory",
+    "exec_interval",
+    "notif_interval",
+    "daily_request_budget",
+    "rules",
+    "stats",
+    "digest",
+    "quiet",
+    "notifications",
+];
+
+#[derive(Clone, Debug)]
+pub enum Origin {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Environment(String),
+    Flag(&'static str),
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "built in default"),
+            Self::System(path) => write!(f, "system file {}", path.display()),
+            Self::User(path) => write!(f, "user file {}", path.display()),
+            Self::Environment(variable) => write!(f, "environment variable {variable}"),
+            Self::Flag(flag) => write!(f, "command line flag {flag}"),
+        }
+    }
+}
+
+// A directory that may hold settings files, and what to call the settings read from it
+type Layer = (Option<PathBuf>, fn(PathBuf) -> Origin);
+
+#[derive(Debug)]
+pub enum SaveError {
+    // The file is there but can't be parsed, so rewriting it would lose settings
+    Unreadable(PathBuf),
+    FailedToWrite(PathBuf, std::io::Error),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(path) => write!(f, "{} could not be parsed, fix it first", path.display()),
+            Self::FailedToWrite(path, e) => write!(f, "could not write {}: {e}", path.display()),
+        }
+    }
+}
+
+// Settings given on the command line, None for any that weren't
+#[derive(Default)]
+pub struct Flags {
+    // With the flag it came from, --city or --location
+    pub q: Option<(Location, &'static str)>,
+    pub days: Option<usize>,
+    pub current: Option<bool>,
+    pub forecast: Option<bool>,
+    pub alerts: Option<bool>,
+    pub marine: Option<bool>,
+    pub exec_interval: Option<Schedule>,
+    pub notif_interval: Option<Schedule>,
+    pub working_directory: Option<PathBuf>,
+}
+
+// The settings in effect and where each of them came from
+pub struct Configuration {
+    pub api: ApiRequestConfiguration,
+    pub daemon: DaemonConfiguration,
+    pub origins: BTreeMap<String, Origin>,
+}
+
+impl Configuration {
+    // Takes one setting from a layer, false if there is no such setting
+    fn set(&mut self, key: &str, api: &ApiRequestConfiguration, daemon: &DaemonConfiguration, origin: Origin) -> bool {
+        match key {
+            "q" => self.api.q = api.q.clone(),
+            "days" => self.api.days = api.days,
+            "hour" => self.api.hour = api.hour,
+            "requests.current" => self.api.requests.current = api.requests.current,
+            "requests.forecast" => self.api.requests.forecast = api.requests.forecast,
+            "requests.alerts" => self.api.requests.alerts = api.requests.alerts,
+            "requests.marine" => self.api.requests.marine = api.requests.marine,
+            "auto_location" => self.api.auto_location = api.auto_location.clone(),
+            "working_directory" => self.daemon.working_directory = daemon.working_directory.clone(),
+            "exec_interval" => self.daemon.exec_interval = daemon.exec_interval.clone(),
+            "notif_interval" => self.daemon.notif_interval = daemon.notif_interval.clone(),
+            "daily_request_budget" => self.daemon.daily_request_budget = daemon.daily_request_budget,
+            "rules" => self.daemon.rules = daemon.rules.clone(),
+            "stats" => self.daemon.stats = daemon.stats.clone(),
+            "digest" => self.daemon.digest = daemon.digest.clone(),
+            "quiet" => self.daemon.quiet = daemon.quiet.clone(),
+            "notifications" => self.daemon.notifications = daemon.notifications.clone(),
+            _ => return false,
+        }
+        self.origins.insert(key.to_string(), origin);
+        true
+    }
+
+    // A setting written the way it would be in its file
+    fn value(&self, key: &str) -> String {
+        let value = match key {
+            "q" => ron::to_string(&self.api.q),
+            "days" => ron::to_string(&self.api.days),
+            "hour" => ron::to_string(&self.api.hour),
+            "requests.current" => ron::to_string(&self.api.requests.current),
+            "requests.forecast" => ron::to_string(&self.api.requests.forecast),
+            "requests.alerts" => ron::to_string(&self.api.requests.alerts),
+            "requests.marine" => ron::to_string(&self.api.requests.marine),
+            "auto_location" => ron::to_string(&self.api.auto_location),
+            "working_directory" => ron::to_string(&self.daemon.working_directory),
+            "exec_interval" => ron::to_string(&self.daemon.exec_interval),
+            "notif_interval" => ron::to_string(&self.daemon.notif_interval),
+            "daily_request_budget" => ron::to_string(&self.daemon.daily_request_budget),
+            "rules" => ron::to_string(&self.daemon.rules),
+            "stats" => ron::to_string(&self.daemon.stats),
+            "digest" => ron::to_string(&self.daemon.digest),
+            "quiet" => ron::to_string(&self.daemon.quiet),
+            "notifications" => ron::to_string(&self.daemon.notifications),
+            _ => return String::new(),
+        };
+        value.unwrap_or_default()
+    }
+}
+
+fn variable(key: &str) -> String {
+    format!("{ENVIRONMENT_PREFIX}{}", key.replace('.', "_").to_uppercase())
+}
+
+// Settings everyone on the machine shares, under the ones in the working directory
+fn system_directory() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("ProgramData").map(|directory| PathBuf::from(directory).join("weathd"))
+    } else {
+        Some(PathBuf::from("/etc/weathd"))
+    }
+}
+
+// --working-directory, then WEATHD_WORKING_DIRECTORY, then ~/.weathd
+pub fn working_directory(flag: Option<&String>) -> PathBuf {
+    match flag.cloned().or_else(|| std::env::var(variable("working_directory")).ok()) {
+        Some(path) => path.into(),
+        None => default_working_directory(),
+    }
+}
+
+// The top level keys a file sets, and the ones inside requests as "requests.alerts"
+fn keys(value: &ron::Value) -> Vec<String> {
+    let ron::Value::Map(map) = value else {
+        return Vec::new();
+    };
+    let mut keys = Vec::new();
+    for (key, value) in map.iter() {
+        let ron::Value::String(key) = key else {
+            continue;
+        };
+        match (key.as_str(), value) {
+            ("requests", ron::Value::Map(fields)) => keys.extend(fields.keys().filter_map(|field| match field {
+                ron::Value::String(field) => Some(format!("requests.{field}")),
+                _ => None,
+            })),
+            _ => keys.push(key.clone()),
+        }
+    }
+    keys
+}
+
+// A file and the settings in it, None if there is no such file or it can't be used
+fn read_file<T: DeserializeOwned>(path: &Path) -> Option<(T, Vec<String>)> {
+    let data = std::fs::read_to_string(path).ok()?;
+    match (ron::from_str::<T>(&data), ron::from_str::<ron::Value>(&data)) {
+        (Ok(config), Ok(value)) => Some((config, keys(&value))),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Failed to parse {}: {:?}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Reads a setting from an environment variable, written as it would be in its file.
+// Strings may leave out their quotes and options their Some, as in WEATHD_EXEC_INTERVAL=10m
+// or WEATHD_DAYS=5, and WEATHD_Q takes anything --location does.
+fn from_environment(key: &str, text: &str) -> Option<(ApiRequestConfiguration, DaemonConfiguration)> {
+    let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+    let snippet = |value: &str| match key.split_once('.') {
+        Some((outer, inner)) => format!("({outer}: ({inner}: {value}))"),
+        None => format!("({key}: {value})"),
+    };
+    for value in [text.to_string(), format!("{text:?}")] {
+        if API_SETTINGS.contains(&key) {
+            if let Ok(api) = options.from_str(&snippet(&value)) {
+                return Some((api, DaemonConfiguration::default()));
+            }
+        } else if let Ok(daemon) = options.from_str(&snippet(&value)) {
+            return Some((ApiRequestConfiguration::default(), daemon));
+        }
+    }
+    if key == "q" {
+        let q = text.parse().ok()?;
+        return Some((
+            ApiRequestConfiguration {
+                q,
+                ..Default::default()
+            },
+            DaemonConfiguration::default(),
+        ));
+    }
+    None
+}
+
+// Works out the settings from, each overriding the ones before: the built in defaults,
+// the system files, the files in the working directory, WEATHD_* environment variables
+// and the flags that were given on the command line
+pub fn resolve(working_directory: &Path, flags: &Flags) -> Configuration {
+    let mut config = Configuration {
+        api: ApiRequestConfiguration {
+            days: Some(DEFAULT_DAYS),
+            ..Default::default()
+        },
+        daemon: DaemonConfiguration {
+            working_directory: working_directory.to_path_buf(),
+            ..Default::default()
+        },
+        origins: BTreeMap::new(),
+    };
+    for key in API_SETTINGS.iter().chain(DAEMON_SETTINGS.iter()) {
+        config.origins.insert(key.to_string(), Origin::Default);
+    }
+
+    let directories: [Layer; 2] = [
+        (system_directory(), Origin::System),
+        (Some(working_directory.to_path_buf()), Origin::User),
+    ];
+    for (directory, origin) in directories {
+        let Some(directory) = directory else {
+            continue;
+        };
+        let path = directory.join(API_CONFIG_FILE);
+        if let Some((api, keys)) = read_file::<ApiRequestConfiguration>(&path) {
+            for key in keys {
+                if !API_SETTINGS.contains(&key.as_str()) || !config.set(&key, &api, &DaemonConfiguration::default(), origin(path.clone())) {
+                    eprintln!("Ignoring unknown setting {key} in {}", path.display());
+                }
+            }
+        }
+        let path = directory.join(DAEMON_CONFIG_FILE);
+        if let Some((daemon, keys)) = read_file::<DaemonConfiguration>(&path) {
+            for key in keys {
+                if !DAEMON_SETTINGS.contains(&key.as_str()) || !config.set(&key, &ApiRequestConfiguration::default(), &daemon, origin(path.clone())) {
+                    eprintln!("Ignoring unknown setting {key} in {}", path.display());
+                }
+            }
+        }
+    }
+
+    for key in API_SETTINGS.iter().chain(DAEMON_SETTINGS.iter()) {
+        let variable = variable(key);
+        let Ok(text) = std::env::var(&variable) else {
+            continue;
+        };
+        match from_environment(key, &text) {
+            Some((api, daemon)) => {
+                config.set(key, &api, &daemon, Origin::Environment(variable));
+            }
+            None => eprintln!("Ignoring {variable}={text:?}, it is not a valid {key}"),
+        }
+    }
+
+    let api = ApiRequestConfiguration {
+        q: flags.q.as_ref().map(|(q, _)| q.clone()).unwrap_or_default(),
+        days: flags.days,
+        requests: RequestTypes {
+            current: flags.current.unwrap_or_default(),
+            forecast: flags.forecast.unwrap_or_default(),
+            alerts: flags.alerts.unwrap_or_default(),
+            marine: flags.marine.unwrap_or_default(),
+        },
+        ..Default::default()
+    };
+    let daemon = DaemonConfiguration {
+        working_directory: flags.working_directory.clone().unwrap_or_default(),
+        exec_interval: flags.exec_interval.clone().unwrap_or_default(),
+        notif_interval: flags.notif_interval.clone().unwrap_or_default(),
+        ..Default::default()
+    };
+    let given = [
+        ("q", flags.q.as_ref().map(|(_, flag)| *flag)),
+        ("days", flags.days.map(|_| "--days")),
+        ("requests.current", flags.current.map(|_| "--current-weather")),
+        ("requests.forecast", flags.forecast.map(|_| "--forecast")),
+        ("requests.alerts", flags.alerts.map(|_| "--alerts")),
+        ("requests.marine", flags.marine.map(|_| "--marine")),
+        ("working_directory", flags.working_directory.as_ref().map(|_| "--working-directory")),
+        ("exec_interval", flags.exec_interval.as_ref().map(|_| "--daemon-update-interval")),
+        ("notif_interval", flags.notif_interval.as_ref().map(|_| "--daemon-notif-interval")),
+    ];
+    for (key, flag) in given {
+        if let Some(flag) = flag {
+            config.set(key, &api, &daemon, Origin::Flag(flag));
+        }
+    }
+    config
+}
+
+pub fn print(config: &Configuration, origins: bool) {
+    let width = API_SETTINGS.iter().chain(DAEMON_SETTINGS.iter()).map(|key| key.len()).max().unwrap_or(0);
+    for (file, settings) in [(API_CONFIG_FILE, &API_SETTINGS[..]), (DAEMON_CONFIG_FILE, &DAEMON_SETTINGS[..])] {
+        println!("{file:=^32}");
+        for key in settings {
+            let value = config.value(key);
+            match config.origins.get(*key).filter(|_| origins) {
+                Some(origin) => println!("{key:<width$} {value}  ({origin})"),
+                None => println!("{key:<width$} {value}"),
+            }
+        }
+        println!();
+    }
+}
+
+// Writes the given settings from config into the files in the working directory. The
+// settings those files already had are kept and nothing else is copied in, so defaults,
+// system files and WEATHD_* variables keep applying as they did.
+pub fn save(working_directory: &Path, config: &Configuration, keys: &[&str]) -> Result<(), SaveError> {
+    let mut user = Configuration {
+        api: ApiRequestConfiguration::default(),
+        daemon: DaemonConfiguration::default(),
+        origins: BTreeMap::new(),
+    };
+    let api_path = working_directory.join(API_CONFIG_FILE);
+    let daemon_path = working_directory.join(DAEMON_CONFIG_FILE);
+    let mut saved = Vec::new();
+    if api_path.exists() {
+        let (api, keys) = read_file(&api_path).ok_or(SaveError::Unreadable(api_path.clone()))?;
+        user.api = api;
+        saved.extend(keys);
+    }
+    if daemon_path.exists() {
+        let (daemon, keys) = read_file(&daemon_path).ok_or(SaveError::Unreadable(daemon_path.clone()))?;
+        user.daemon = daemon;
+        saved.extend(keys);
+    }
+    for key in keys {
+        if user.set(key, &config.api, &config.daemon, Origin::Default) && !saved.iter().any(|k| k == key) {
+            saved.push(key.to_string());
+        }
+    }
+
+    for (path, settings) in [(api_path, &API_SETTINGS[..]), (daemon_path, &DAEMON_SETTINGS[..])] {
+        if !settings.iter().any(|key| keys.contains(key)) {
+            continue;
+        }
+        let mut fields = Vec::new();
+        let mut requests = Vec::new();
+        for key in settings.iter().filter(|key| saved.iter().any(|k| k == *key)) {
+            match key.strip_prefix("requests.") {
+                Some(field) => requests.push(format!("{field}: {}", user.value(key))),
+                None => fields.push(format!("{key}: {}", user.value(key))),
+            }
+        }
+        if !requests.is_empty() {
+            fields.push(format!("requests: ({})", requests.join(", ")));
+        }
+        std::fs::write(&path, format!("({})\n", fields.join(", "))).map_err(|e| SaveError::FailedToWrite(path, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // resolve reads every WEATHD_* variable, so tests that set them take turns
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("weathd-config-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn unset_flags_keep_the_file() {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("file");
+        std::fs::write(directory.join(API_CONFIG_FILE), "(days: Some(4), requests: (alerts: true))").unwrap();
+        let config = resolve(&directory, &Flags::default());
+        assert_eq!(config.api.days, Some(4));
+        assert!(config.api.requests.alerts);
+        assert!(config.api.requests.current);
+        assert!(matches!(config.origins["days"], Origin::User(_)));
+        assert!(matches!(config.origins["requests.current"], Origin::Default));
+    }
+
+    #[test]
+    fn environment_over_file_and_flags_over_environment() {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("layers");
+        std::fs::write(directory.join(API_CONFIG_FILE), "(days: Some(4))").unwrap();
+        std::env::set_var("WEATHD_DAYS", "5");
+        let config = resolve(&directory, &Flags::default());
+        let flagged = resolve(
+            &directory,
+            &Flags {
+                days: Some(7),
+                ..Default::default()
+            },
+        );
+        std::env::remove_var("WEATHD_DAYS");
+        assert_eq!(config.api.days, Some(5));
+        assert!(matches!(&config.origins["days"], Origin::Environment(variable) if variable == "WEATHD_DAYS"));
+        assert_eq!(flagged.api.days, Some(7));
+        assert!(matches!(flagged.origins["days"], Origin::Flag("--days")));
+    }
+
+    #[test]
+    fn environment_variables_parse() {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("environment");
+        std::env::set_var("WEATHD_REQUESTS_ALERTS", "true");
+        std::env::set_var("WEATHD_Q", "48.8,2.3");
+        let config = resolve(&directory, &Flags::default());
+        std::env::set_var("WEATHD_Q", "City(\"Paris\")");
+        let written = resolve(&directory, &Flags::default());
+        std::env::remove_var("WEATHD_REQUESTS_ALERTS");
+        std::env::remove_var("WEATHD_Q");
+        assert!(config.api.requests.alerts);
+        assert!(matches!(config.api.q, Location::Coordinate(lat, lon) if lat == 48.8 && lon == 2.3));
+        assert!(matches!(&written.api.q, Location::City(city) if city == "Paris"));
+    }
+
+    #[test]
+    fn saving_keeps_the_other_settings() {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|e| e.into_inner());
+        let directory = directory("save");
+        std::fs::write(directory.join(API_CONFIG_FILE), "(days: Some(4), requests: (alerts: true))").unwrap();
+        let flags = Flags {
+            q: Some((Location::City(String::from("Paris")), "--city")),
+            marine: Some(true),
+            ..Default::default()
+        };
+        save(&directory, &resolve(&directory, &flags), &["q", "requests.marine"]).unwrap();
+        let config = resolve(&directory, &Flags::default());
+        assert!(matches!(&config.api.q, Location::City(city) if city == "Paris"));
+        assert_eq!(config.api.days, Some(4));
+        assert!(config.api.requests.alerts);
+        assert!(config.api.requests.marine);
+        assert!(matches!(config.origins["requests.current"], Origin::Default));
+        assert!(!directory.join(DAEMON_CONFIG_FILE).exists());
+    }
+}
@@ -1,17 +1,56 @@
 use crate::api::*;
+use crate::digest::{self, Channel, DigestConfiguration};
+use crate::facts::Facts;
+use crate::geolocate;
+use crate::history::{self, ForecastRecord, MarineRecord, Observation};
+use crate::locations;
+use crate::models::{Alert, Current};
+use crate::notify::{self, Message, NotificationConfiguration, Notifier};
+use crate::quiet::{self, DoNotDisturb, QuietConfiguration, Severity};
+use crate::rules::{Rule, RuleState};
+use crate::stats::{self, StatsConfiguration};
+use crate::units::{with_unit, DisplayUnits};
+use crate::utils::Schedule;
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use std::{
     io::Write,
-    path::PathBuf,
-    thread::sleep,
-    time::{Duration, Instant},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+// How often stations on Location::Auto check whether the machine has moved
+const MOVE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct DaemonConfiguration {
     pub working_directory: PathBuf,
-    pub exec_interval: Duration,
-    pub notif_interval: Duration,
+    // When to poll and when to send the current weather, see Schedule
+    pub exec_interval: Schedule,
+    pub notif_interval: Schedule,
+    pub rules: Vec<Rule>,
+    pub stats: StatsConfiguration,
+    pub daily_request_budget: Option<u32>,
+    pub digest: DigestConfiguration,
+    pub quiet: QuietConfiguration,
+    pub notifications: NotificationConfiguration,
+}
+
+impl Default for DaemonConfiguration {
+    fn default() -> Self {
+        Self {
+            working_directory: PathBuf::default(),
+            exec_interval: Duration::new(600, 0).into(),
+            notif_interval: Duration::new(21600, 0).into(),
+            rules: Vec::new(),
+            stats: StatsConfiguration::default(),
+            daily_request_budget: None,
+            digest: DigestConfiguration::default(),
+            quiet: QuietConfiguration::default(),
+            notifications: NotificationConfiguration::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -45,10 +84,8 @@ fn daemonize_unix(config: &DaemonConfiguration) -> Result<(), DaemonizationError
 
     let path = PathBuf::from(working_directory);
 
-    if let Err(_) = File::open(path.clone()) {
-        if let Err(_) = std::fs::create_dir(path.clone()) {
-            return Err(DaemonizationError::FailureToCreateWorkingDirectory);
-        };
+    if File::open(path.clone()).is_err() && std::fs::create_dir(path.clone()).is_err() {
+        return Err(DaemonizationError::FailureToCreateWorkingDirectory);
     }
 
     let log = path.join("log");
@@ -61,7 +98,7 @@ fn daemonize_unix(config: &DaemonConfiguration) -> Result<(), DaemonizationError
     let Ok(log_err_file) = File::create(&log_err) else {
         return Err(DaemonizationError::FailureToCreateLogFile);
     };
-    if let Ok(_) = File::open(&pid) {
+    if File::open(&pid).is_ok() {
         if let Err(e) = terminate(pid.clone()) {
             eprintln!("Failed to terminate existing instance: {:?}", e);
             return Err(DaemonizationError::ExistingInstance);
@@ -130,148 +167,571 @@ pub fn default_working_directory() -> PathBuf {
     }
 }
 
-pub fn daemon_main(
-    mut api: Api,
-    daemon_config: DaemonConfiguration,
+// Everything the daemon keeps track of for one location it polls
+struct Station {
+    // None for the location in api_config.ron, which caches into the working directory
+    name: Option<String>,
+    directory: PathBuf,
+    location: String,
+    api: Api,
     api_config: ApiRequestConfiguration,
-) {
-    use notify_rust::Notification;
-    let start = Instant::now();
-    let mut last_iteration = start.clone();
-    let mut last_notif = last_iteration - daemon_config.notif_interval;
+    rules: Vec<Rule>,
+    silent: bool,
+    // Overrides from locations.ron, None uses the daemon's
+    channels: Option<Vec<Channel>>,
+    dnd: Option<DoNotDisturb>,
+    // Only as far back as the stats and digest need, older days are summarized in earlier
+    observations: Vec<Observation>,
+    earlier: stats::Earlier,
+    last_observation_time: Option<i64>,
+    last_marine_time: Option<i64>,
+    rule_state: RuleState,
+    // Where the last poll was for, as the provider resolved it
+    position: Option<(f64, f64)>,
+    // Where the machine was found to be for the next poll, when following it
+    fix: Option<Location>,
+}
 
-    loop {
-        let response = api.make_request(&api_config);
+impl Station {
+    fn new(
+        name: Option<String>,
+        directory: PathBuf,
+        api: Api,
+        api_config: ApiRequestConfiguration,
+        rules: Vec<Rule>,
+        working_directory: &Path,
+    ) -> Self {
+        // History is shared, named locations are recorded under their name
+        let location = name.clone().unwrap_or(format!("{}", api_config.q));
+        let observations = history::load_observations(working_directory)
+            .into_iter()
+            .filter(|o| o.location == location)
+            .collect();
+        Self {
+            name,
+            directory,
+            location,
+            api,
+            api_config,
+            rules,
+            silent: false,
+            channels: None,
+            dnd: None,
+            observations,
+            earlier: stats::Earlier::default(),
+            last_observation_time: None,
+            last_marine_time: None,
+            rule_state: RuleState::default(),
+            position: None,
+            fix: None,
+        }
+    }
+
+    fn follows(&self) -> bool {
+        matches!(self.api_config.q, Location::Auto)
+    }
 
-        if let Some(Err(e)) = response.current {
-            println!("{}", chrono::Local::now());
-            println!("Get Current Weather Api Call failed with: {e:?}");
+    // True once where the machine is now is far enough from where the last poll was
+    // for that the weather should be fetched again
+    fn moved(&mut self, (q, source): &(Location, geolocate::Source)) -> bool {
+        if !self.follows() {
+            return false;
         }
+        let (Location::Coordinate(lat, lon), Some(position)) = (q, self.position) else {
+            return false;
+        };
+        let distance = geolocate::distance_km(position, (*lat, *lon));
+        if distance < self.api_config.auto_location.move_km {
+            return false;
+        }
+        self.log(&format!("Moved {distance:.1}km according to {source}, updating"));
+        self.fix = Some(q.clone());
+        true
+    }
 
-        if let Some(Err(e)) = response.alerts {
-            println!("{}", chrono::Local::now());
-            println!("Get Weather Alerts Api Call failed with: {e:?}");
+    fn title(&self, title: &str) -> String {
+        match &self.name {
+            Some(name) => format!("{title}: {name}"),
+            None => title.to_string(),
         }
+    }
 
-        if let Some(Err(e)) = response.forecast {
-            println!("{}", chrono::Local::now());
-            println!("Get Forecast Api Call failed with: {e:?}");
+    fn log(&self, message: &str) {
+        println!("{}", chrono::Local::now());
+        match &self.name {
+            Some(name) => println!("[{name}] {message}"),
+            None => println!("{message}"),
         }
+    }
 
-        if last_notif.elapsed() >= daemon_config.notif_interval {
-            last_notif = Instant::now();
-            if let Some((response, _timestamp)) = api.get_cached_current() {
-                let mut notification = Vec::new();
+    // API calls made by each poll
+    fn calls(&self) -> u32 {
+        let requests = &self.api_config.requests;
+        [requests.current, requests.forecast, requests.alerts, requests.marine]
+            .iter()
+            .filter(|enabled| **enabled)
+            .count() as u32
+    }
 
-                writeln!(
-                    notification,
-                    "Current Tempurature (Imperial): {}°F, Feels like: {}°F",
-                    response["current"]["temp_f"], response["current"]["feelslike_f"]
-                )
-                    .unwrap();
+    fn poll(&mut self, daemon_config: &DaemonConfiguration, units: &DisplayUnits, notifier: &mut Notifier) {
+        let working_directory = &daemon_config.working_directory;
 
-                writeln!(
-                    notification,
-                    "Current Tempurature (Metric): {}°C, Feels like: {}°C",
-                    response["current"]["temp_c"], response["current"]["feelslike_c"]
-                )
-                    .unwrap();
+        let mut api_config = self.api_config.clone();
+        if self.follows() {
+            api_config.q = self
+                .fix
+                .take()
+                .unwrap_or_else(|| geolocate::resolve(&api_config.auto_location).0);
+        }
 
-                writeln!(
-                    notification,
-                    "Wind Speed (Imperial): {} mph, from {}",
-                    response["current"]["wind_mph"], response["current"]["wind_dir"]
-                )
-                    .unwrap();
+        let response = self.api.make_request(&api_config);
+        let forecast_fetched = matches!(response.forecast, Some(Ok(_)));
+        let failures = [
+            ("Get Current Weather", response.current.and_then(Result::err)),
+            ("Get Weather Alerts", response.alerts.and_then(Result::err)),
+            ("Get Forecast", response.forecast.and_then(Result::err)),
+            ("Get Marine Forecast", response.marine.and_then(Result::err)),
+        ];
+        let failures: Vec<String> = failures
+            .into_iter()
+            .filter_map(|(request, error)| Some(format!("{request} Api Call failed with: {:?}", error?)))
+            .collect();
+        for failure in failures {
+            self.log(&failure);
+        }
 
-                writeln!(
-                    notification,
-                    "Wind Speed (Metric): {} kph, from {}",
-                    response["current"]["wind_kph"], response["current"]["wind_dir"]
-                )
-                    .unwrap();
+        if let Some(observation) = self
+            .api
+            .get_cached_current()
+            .and_then(|(response, _timestamp)| Observation::from_current(PROVIDER, &self.location, response))
+        {
+            if self.last_observation_time != Some(observation.time) {
+                self.last_observation_time = Some(observation.time);
+                if let Err(e) = history::record_observation(working_directory, &observation) {
+                    self.log(&format!("Failed to record observation: {e:?}"));
+                }
+                self.observations.push(observation);
+            }
+        }
 
-                writeln!(
-                    notification,
-                    "Wind Chill (Imperial): {}°F",
-                    response["current"]["windchill_f"]
-                )
-                    .unwrap();
+        if forecast_fetched {
+            if let Some((response, _timestamp)) = self.api.get_cached_forecast() {
+                let forecasts = ForecastRecord::from_forecast(
+                    PROVIDER,
+                    &self.location,
+                    chrono::Utc::now().timestamp(),
+                    response,
+                );
+                if !forecasts.is_empty() {
+                    if let Err(e) = history::record_forecasts(working_directory, &forecasts) {
+                        self.log(&format!("Failed to record forecast: {e:?}"));
+                    }
+                }
+            }
+        }
 
-                writeln!(
-                    notification,
-                    "Wind Chill (Metric): {}°C",
-                    response["current"]["windchill_c"]
-                )
-                    .unwrap();
+        if let Some(record) = self
+            .api
+            .get_cached_marine()
+            .and_then(|(response, _timestamp)| MarineRecord::from_marine(PROVIDER, &self.location, response))
+        {
+            if self.last_marine_time != Some(record.time) {
+                self.last_marine_time = Some(record.time);
+                if let Err(e) = history::record_marine(working_directory, &record) {
+                    self.log(&format!("Failed to record marine conditions: {e:?}"));
+                }
+            }
+        }
 
-                writeln!(
-                    notification,
-                    "Humidity: {}%",
-                    response["current"]["humidity"]
-                )
-                    .unwrap();
+        self.position = [self.api.get_cached_current(), self.api.get_cached_forecast()]
+            .into_iter()
+            .flatten()
+            .find_map(|(response, _timestamp)| {
+                Some((
+                    response["location"]["lat"].as_f64()?,
+                    response["location"]["lon"].as_f64()?,
+                ))
+            });
+
+        if let Err(e) = std::fs::create_dir_all(&self.directory)
+            .and_then(|_| self.api.save_cache(&self.directory))
+        {
+            self.log(&format!("Failed to save response cache: {e:?}"));
+        }
 
-                writeln!(
-                    notification,
-                    "Pressure (Imperial): {}in",
-                    response["current"]["pressure_in"]
-                )
-                    .unwrap();
+        let mut facts = Facts::from_api(&self.api);
+        self.earlier.absorb(&mut self.observations, daemon_config.stats.trend_window);
+        stats::add_facts(&mut facts, &self.earlier, &self.observations, &daemon_config.stats);
+        units.add_facts(&mut facts);
+
+        let rules: Vec<Rule> = daemon_config.rules.iter().chain(&self.rules).cloned().collect();
+        let triggered: Vec<Rule> = self
+            .rule_state
+            .newly_triggered(&rules, &facts)
+            .into_iter()
+            .cloned()
+            .collect();
+        if self.silent {
+            return;
+        }
+        for rule in triggered {
+            let message = Message {
+                title: self.title(&rule.name),
+                body: rule.message(&facts),
+                severity: rule.severity,
+                replaces: Some(format!("rule {}", rule.name)),
+                ..self.message()
+            };
+            self.notify(notifier, message, "rule");
+        }
+    }
 
-                writeln!(
-                    notification,
-                    "Pressure (Metric): {}mb",
-                    response["current"]["pressure_mb"]
-                )
-                    .unwrap();
+    // A notification about this station, with the current condition's icon
+    fn message(&self) -> Message {
+        let condition = self
+            .api
+            .get_cached_current()
+            .and_then(|(response, _timestamp)| response["current"]["condition"]["text"].as_str());
+        Message {
+            icon: condition.map(String::from),
+            forecast: Some(self.name.clone()),
+            ..Default::default()
+        }
+    }
 
-                writeln!(
-                    notification,
-                    "Condition: {}",
-                    response["current"]["condition"]["text"]
-                )
-                    .unwrap();
-
-                let notification = String::from_utf8(notification)
-                    .expect("Failed to format current weather notification");
-
-                if let Err(e) = Notification::new()
-                    .summary("Current Weather")
-                        .body(&notification)
-                        .show()
-                {
-                    println!("{}", chrono::Local::now());
-                    println!("Failed to send current weather notification: {e:?}");
+    fn notify(&mut self, notifier: &mut Notifier, message: Message, kind: &str) {
+        let message = Message {
+            replaces: message.replaces.map(|key| format!("{}: {key}", self.location)),
+            ..message
+        };
+        let result = match &mut self.dnd {
+            Some(dnd) if !dnd.allows(message.severity) => {
+                dnd.hold(&message.title);
+                Ok(())
+            }
+            Some(_) => notifier.show(message),
+            None => notifier.send(message),
+        };
+        if let Err(e) = result {
+            self.log(&format!("Failed to send {kind} notification: {e:?}"));
+        }
+    }
+
+    // The forecast popup the Show forecast button asks for
+    fn notify_forecast(&self, config: &DigestConfiguration, units: &DisplayUnits, notifier: &mut Notifier) {
+        let Some(body) = digest::compose(&self.api, &self.observations, config, units) else {
+            return;
+        };
+        let message = Message {
+            title: self.title("Forecast"),
+            body,
+            forecast: None,
+            replaces: Some(format!("{}: forecast", self.location)),
+            ..self.message()
+        };
+        if let Err(e) = notifier.show(message) {
+            self.log(&format!("Failed to send forecast notification: {e:?}"));
+        }
+    }
+
+    fn notify_digest(&mut self, config: &DigestConfiguration, units: &DisplayUnits, notifier: &mut Notifier) {
+        if self.silent {
+            return;
+        }
+        let Some(body) = digest::compose(&self.api, &self.observations, config, units) else {
+            self.log("No forecast to make a digest from");
+            return;
+        };
+        let title = self.title(config.title(chrono::Local::now()));
+        let dnd = self.dnd.as_mut().unwrap_or(&mut notifier.dnd);
+        if !dnd.allows(Severity::Minor) {
+            dnd.hold(&title);
+            return;
+        }
+        let channels = self.channels.clone().unwrap_or_else(|| config.channels.clone());
+        for channel in &channels {
+            if *channel == Channel::Desktop {
+                let message = Message {
+                    title: title.clone(),
+                    body: body.clone(),
+                    replaces: Some(String::from("digest")),
+                    ..self.message()
                 };
+                self.notify(notifier, message, "digest");
+            } else if let Err(e) = digest::deliver(channel, &title, &body) {
+                self.log(&format!("Failed to send digest to {channel:?}: {e}"));
             }
+        }
+    }
 
-            if let Some((response, _timestamp)) = api.get_cached_alerts() {
-                let alert_array = &response["alerts"]["alert"];
-                if !alert_array.is_array() {
-                    panic!("Received Invalid API alert response");
-                }
-                for alert in (0..).map(|i| &alert_array[i]) {
-                    if alert.is_null() {
-                        break;
-                    }
+    fn notify_summary(&mut self, units: &DisplayUnits, notifier: &mut Notifier) {
+        if self.silent {
+            return;
+        }
 
-                    if let Err(e) = Notification::new()
-                        .summary(format!("{}", alert["headline"]).as_ref())
-                            .body(format!("{}", alert["instruction"]).as_ref())
-                            .show()
-                    {
-                        println!("{}", chrono::Local::now());
-                        println!("Failed to send weather alerts notification: {e:?}");
-                    };
-                }
+        if let Some((response, _timestamp)) = self.api.get_cached_current() {
+            let current = Current::from_json(response);
+            let (temperature, speed) = (units.temperature(), units.speed());
+            let mut notification = Vec::new();
+
+            writeln!(
+                notification,
+                "Current Temperature: {}, Feels like: {}",
+                with_unit(temperature, current.temp_c),
+                with_unit(temperature, current.feelslike_c)
+            )
+                .unwrap();
+
+            writeln!(
+                notification,
+                "Wind Speed: {}, from {}",
+                with_unit(speed, current.wind_kph),
+                current.wind_dir.as_deref().unwrap_or("-")
+            )
+                .unwrap();
+
+            writeln!(
+                notification,
+                "Wind Chill: {}",
+                with_unit(temperature, current.windchill_c)
+            )
+                .unwrap();
+
+            writeln!(
+                notification,
+                "Humidity: {}%",
+                response["current"]["humidity"]
+            )
+                .unwrap();
+
+            writeln!(
+                notification,
+                "Pressure: {}",
+                with_unit(units.pressure(), current.pressure_mb)
+            )
+                .unwrap();
+
+            writeln!(
+                notification,
+                "Condition: {}",
+                response["current"]["condition"]["text"]
+            )
+                .unwrap();
+
+            let notification = String::from_utf8(notification)
+                .expect("Failed to format current weather notification");
+
+            let message = Message {
+                title: self.title("Current Weather"),
+                body: notification,
+                replaces: Some(String::from("current weather")),
+                ..self.message()
+            };
+            self.notify(notifier, message, "current weather");
+        }
+
+        let mut messages = Vec::new();
+        if let Some((response, _timestamp)) = self.api.get_cached_alerts() {
+            for alert in Alert::list_from_json(response) {
+                messages.push(Message {
+                    title: alert.headline.unwrap_or(String::from("-")),
+                    body: alert.instruction.unwrap_or_default(),
+                    severity: Severity::from_alert(alert.severity.as_deref()),
+                    icon: Some(String::from("alert")),
+                    ..self.message()
+                });
             }
+        }
+        for message in messages {
+            self.notify(notifier, message, "weather alerts");
+        }
+    }
+}
+
+// Where the machine is, looked up once for all the stations following it as gpsd and
+// GeoClue can take seconds to answer
+fn locate(stations: &[Station]) -> Option<(Location, geolocate::Source)> {
+    let station = stations.iter().find(|station| station.follows())?;
+    Some(geolocate::resolve(&station.api_config.auto_location))
+}
+
+// Sends one notification summing up what quiet hours or a snooze held back, once they're over
+fn release_held(held: Option<(String, String)>, notifier: &mut Notifier) {
+    if let Some((title, body)) = held {
+        println!("{}", chrono::Local::now());
+        println!("{title}\n{body}");
+        let message = Message {
+            title,
+            body,
+            ..Default::default()
+        };
+        if let Err(e) = notifier.show(message) {
+            println!("Failed to send held back notifications: {e:?}");
+        }
+    }
+}
 
-            let next_iteration = last_iteration + daemon_config.exec_interval;
-            last_iteration = Instant::now();
+// Acts on a button pressed on one of the notifications
+fn invoked(
+    invoked: notify::Invoked,
+    stations: &[Station],
+    daemon_config: &DaemonConfiguration,
+    units: &DisplayUnits,
+    notifier: &mut Notifier,
+) {
+    match invoked.action.as_str() {
+        notify::SNOOZE_ACTION => match quiet::snooze(&daemon_config.working_directory, notify::SNOOZE_BUTTON) {
+            Ok(Some(until)) => println!("Snoozed until {}", until.format("%H:%M")),
+            Ok(None) => {}
+            Err(e) => println!("Failed to snooze: {e:?}"),
+        },
+        notify::FORECAST_ACTION => {
+            if let Some(station) = stations.iter().find(|station| station.name == invoked.station) {
+                station.notify_forecast(&daemon_config.digest, units, notifier);
+            }
+        }
+        _ => {}
+    }
+}
 
-            if next_iteration > last_iteration {
-                sleep(next_iteration - last_iteration);
+// Polls every location in locations.ron, or the one in api_config.ron when there are
+// none. A daily_request_budget stretches the update interval so all of them fit in it.
+pub fn daemon_main(
+    api: Api,
+    daemon_config: DaemonConfiguration,
+    api_config: ApiRequestConfiguration,
+    units: DisplayUnits,
+) {
+    let working_directory = &daemon_config.working_directory;
+    let named = locations::load(working_directory);
+    let mut stations: Vec<Station> = if named.is_empty() {
+        vec![Station::new(
+            None,
+            working_directory.clone(),
+            api,
+            api_config.clone(),
+            Vec::new(),
+            working_directory,
+        )]
+    } else {
+        named
+            .iter()
+            .map(|location| Station {
+                silent: location.silent,
+                channels: location.channels.clone(),
+                dnd: location
+                    .quiet
+                    .clone()
+                    .map(|quiet| DoNotDisturb::new(quiet, working_directory.clone())),
+                ..Station::new(
+                    Some(location.name.clone()),
+                    location.directory(working_directory),
+                    api.with_key(),
+                    location.api_config(&api_config),
+                    location.rules.clone(),
+                    working_directory,
+                )
+            })
+            .collect()
+    };
+
+    let calls: u32 = stations.iter().map(Station::calls).sum();
+    // A daily_request_budget keeps at least this long between polls
+    let mut min_gap = Duration::ZERO;
+    if let Some(budget) = daemon_config.daily_request_budget.filter(|budget| *budget > 0) {
+        min_gap = Duration::from_secs_f64(86400.0 * calls as f64 / budget as f64);
+        let now = Local::now();
+        let gap = daemon_config
+            .exec_interval
+            .next(now)
+            .and_then(|next| (next - now).to_std().ok());
+        if gap.is_some_and(|gap| gap < min_gap) {
+            println!(
+                "Polling at most every {}s instead of {} to stay within {budget} requests a day",
+                min_gap.as_secs(),
+                daemon_config.exec_interval
+            );
+        }
+    }
+
+    let mut last_notif: Option<DateTime<Local>> = None;
+    let mut last_digest_check = Local::now();
+    let mut notifier = Notifier::new(
+        &daemon_config.notifications,
+        DoNotDisturb::new(daemon_config.quiet.clone(), working_directory.clone()),
+        working_directory,
+    );
+
+    loop {
+        let iteration = Local::now();
+
+        let here = locate(&stations);
+        for station in stations.iter_mut() {
+            if station.follows() {
+                station.fix = here.as_ref().map(|(q, _source)| q.clone());
+            }
+            station.poll(&daemon_config, &units, &mut notifier);
+        }
+
+        // Stations following the machine around wake up in between to check for moves
+        let step = if stations.iter().any(Station::follows) {
+            MOVE_CHECK_INTERVAL
+        } else {
+            Duration::MAX
+        };
+        let next_iteration = daemon_config
+            .exec_interval
+            .next(iteration)
+            .map(|next| next.max(iteration + min_gap));
+        loop {
+            let now = Local::now();
+            if last_notif.is_none_or(|last| daemon_config.notif_interval.due(last, now)) {
+                last_notif = Some(now);
+                for station in stations.iter_mut() {
+                    station.notify_summary(&units, &mut notifier);
+                }
+            }
+            if daemon_config.digest.schedule.due(last_digest_check, now) {
+                for station in stations.iter_mut() {
+                    station.notify_digest(&daemon_config.digest, &units, &mut notifier);
+                }
+            }
+            last_digest_check = now;
+            release_held(notifier.dnd.release(), &mut notifier);
+            for station in stations.iter_mut() {
+                let held = station.dnd.as_mut().and_then(DoNotDisturb::release);
+                release_held(held, &mut notifier);
+            }
+
+            let until = |next: DateTime<Local>| (next - now).to_std().unwrap_or(Duration::ZERO);
+            let remaining = next_iteration.map_or(Duration::MAX, until);
+            if remaining.is_zero() {
+                break;
+            }
+            // Also wake up in time for the next summary and digest, and often while
+            // holding notifications back so they go out soon after quiet hours end
+            let wake = [
+                last_notif.and_then(|last| daemon_config.notif_interval.next(last)),
+                daemon_config.digest.schedule.next(now),
+            ]
+            .into_iter()
+            .flatten()
+            .map(until)
+            .fold(remaining.min(step), Duration::min);
+            let holding = notifier.dnd.holding()
+                || stations.iter().any(|station| station.dnd.as_ref().is_some_and(DoNotDisturb::holding));
+            let wake = if holding { MOVE_CHECK_INTERVAL.min(wake) } else { wake };
+            // Buttons pressed on notifications are acted on straight away
+            if let Some(action) = notifier.wait(wake) {
+                invoked(action, &stations, &daemon_config, &units, &mut notifier);
+                continue;
+            }
+            if let Some(here) = locate(&stations) {
+                for station in stations.iter_mut() {
+                    if station.moved(&here) {
+                        station.poll(&daemon_config, &units, &mut notifier);
+                    }
+                }
             }
         }
     }
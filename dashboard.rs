@@ -0,0 +1,407 @@
+use crate::api::Api;
+use crate::history;
+use crate::models::*;
+use crate::units::{with_unit, DisplayUnits, Unit};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Axis, Bar, BarChart, BarGroup, Block, Chart, Dataset, GraphType, Paragraph, Row, Table,
+        Tabs, Wrap,
+    },
+    DefaultTerminal, Frame,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::new(5, 0);
+
+// Each location the daemon keeps a cache for
+#[derive(Debug, Clone)]
+pub struct DashboardLocation {
+    pub name: String,
+    pub directory: PathBuf,
+}
+
+struct DaemonStatus {
+    pid: Option<String>,
+    running: bool,
+    observations: usize,
+}
+
+struct State {
+    locations: Vec<DashboardLocation>,
+    selected: usize,
+    api: Api,
+    status: DaemonStatus,
+    alert_scroll: u16,
+    last_refresh: Instant,
+    units: DisplayUnits,
+}
+
+fn daemon_status(working_directory: &Path) -> DaemonStatus {
+    let pid = std::fs::read_to_string(working_directory.join("pid"))
+        .ok()
+        .map(|pid| pid.trim().to_string());
+    // kill -0 only checks that the process exists
+    let running = cfg!(unix)
+        && pid.as_ref().is_some_and(|pid| {
+            std::process::Command::new("kill")
+                .args(["-0", pid])
+                .output()
+                .is_ok_and(|output| output.status.success())
+        });
+    DaemonStatus {
+        pid,
+        running,
+        observations: history::load_observations(working_directory).len(),
+    }
+}
+
+impl State {
+    fn refresh(&mut self, working_directory: &Path) {
+        if let Some(location) = self.locations.get(self.selected) {
+            self.api.load_cache(&location.directory);
+        }
+        self.status = daemon_status(working_directory);
+        self.last_refresh = Instant::now();
+    }
+}
+
+pub fn run(
+    working_directory: &Path,
+    locations: Vec<DashboardLocation>,
+    units: DisplayUnits,
+) -> std::io::Result<()> {
+    let mut state = State {
+        locations,
+        selected: 0,
+        api: Api::new(String::new()),
+        status: daemon_status(working_directory),
+        alert_scroll: 0,
+        last_refresh: Instant::now(),
+        units,
+    };
+    state.refresh(working_directory);
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut state, working_directory);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut State,
+    working_directory: &Path,
+) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, state))?;
+
+        let timeout = REFRESH_INTERVAL.saturating_sub(state.last_refresh.elapsed());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let count = state.locations.len().max(1);
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => {
+                        state.selected = (state.selected + 1) % count;
+                        state.alert_scroll = 0;
+                        state.refresh(working_directory);
+                    }
+                    KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => {
+                        state.selected = (state.selected + count - 1) % count;
+                        state.alert_scroll = 0;
+                        state.refresh(working_directory);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        state.alert_scroll = state.alert_scroll.saturating_add(1)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        state.alert_scroll = state.alert_scroll.saturating_sub(1)
+                    }
+                    KeyCode::Char('r') => state.refresh(working_directory),
+                    _ => {}
+                }
+            }
+        }
+
+        if state.last_refresh.elapsed() >= REFRESH_INTERVAL {
+            state.refresh(working_directory);
+        }
+    }
+}
+
+fn show<T: std::fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+fn draw(frame: &mut Frame, state: &State) {
+    let [tabs, top, middle, bottom, help] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(9),
+        Constraint::Min(10),
+        Constraint::Min(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [current, status] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(top);
+    let [temperature, precipitation] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(middle);
+    let [forecast, alerts] =
+        Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(bottom);
+
+    let titles: Vec<String> = state.locations.iter().map(|l| l.name.clone()).collect();
+    frame.render_widget(
+        Tabs::new(titles)
+            .select(state.selected)
+            .block(Block::bordered().title("Locations"))
+            .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        tabs,
+    );
+
+    draw_current(frame, state, current);
+    draw_status(frame, state, status);
+    draw_hourly(frame, state, temperature, precipitation);
+    draw_forecast(frame, state, forecast);
+    draw_alerts(frame, state, alerts);
+
+    frame.render_widget(
+        Paragraph::new("←/→ switch location  ↑/↓ scroll alerts  r refresh  q quit")
+            .style(Style::default().fg(Color::DarkGray)),
+        help,
+    );
+}
+
+fn draw_current(frame: &mut Frame, state: &State, area: Rect) {
+    let block = Block::bordered().title("Current Weather");
+    let Some((response, timestamp)) = state.api.get_cached_current() else {
+        frame.render_widget(Paragraph::new("No cached weather yet").block(block), area);
+        return;
+    };
+    let location = LocationInfo::from_json(response);
+    let current = Current::from_json(response);
+    let units = &state.units;
+    let (temperature, speed) = (units.temperature(), units.speed());
+    let lines = vec![
+        Line::from(vec![
+            Span::styled(
+                with_unit(temperature, current.temp_c),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("  {}", show(&current.condition))),
+        ]),
+        Line::from(format!("Feels like {}", with_unit(temperature, current.feelslike_c))),
+        Line::from(format!(
+            "Wind {} from {}, gusting {}",
+            with_unit(speed, current.wind_kph),
+            show(&current.wind_dir),
+            with_unit(speed, current.gust_kph)
+        )),
+        Line::from(format!(
+            "Humidity {}%  Pressure {}",
+            show(&current.humidity),
+            with_unit(units.pressure(), current.pressure_mb)
+        )),
+        Line::from(format!(
+            "Cloud {}%  UV {}  Visibility {}",
+            show(&current.cloud),
+            show(&current.uv),
+            with_unit(units.distance(), current.vis_km)
+        )),
+        Line::from(Span::styled(
+            format!(
+                "{}, {} — updated {} ({} min ago)",
+                show(&location.name),
+                show(&location.country),
+                show(&current.last_updated),
+                timestamp.elapsed().as_secs() / 60
+            ),
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_status(frame: &mut Frame, state: &State, area: Rect) {
+    let status = &state.status;
+    let (text, color) = if status.running {
+        ("running", Color::Green)
+    } else {
+        ("not running", Color::Red)
+    };
+    let age = |cache: Option<&(json::JsonValue, Instant)>| match cache {
+        Some((_, timestamp)) => format!("{} min ago", timestamp.elapsed().as_secs() / 60),
+        None => String::from("never"),
+    };
+    let lines = vec![
+        Line::from(vec![Span::raw("Daemon: "), Span::styled(text, Style::default().fg(color))]),
+        Line::from(format!("PID: {}", show(&status.pid))),
+        Line::from(format!("Current: {}", age(state.api.get_cached_current()))),
+        Line::from(format!("Forecast: {}", age(state.api.get_cached_forecast()))),
+        Line::from(format!("Alerts: {}", age(state.api.get_cached_alerts()))),
+        Line::from(format!("Observations recorded: {}", status.observations)),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Daemon")),
+        area,
+    );
+}
+
+fn draw_hourly(frame: &mut Frame, state: &State, temperature: Rect, precipitation: Rect) {
+    let hours = state
+        .api
+        .get_cached_forecast()
+        .map(|(response, _)| Hour::upcoming(response, 24))
+        .unwrap_or_default();
+    if hours.is_empty() {
+        let block = Block::bordered().title("Next 24 Hours");
+        frame.render_widget(Paragraph::new("No hourly forecast cached").block(block), temperature);
+        return;
+    }
+
+    let unit = state.units.temperature();
+    let points: Vec<(f64, f64)> = hours
+        .iter()
+        .enumerate()
+        .filter_map(|(i, hour)| Some((i as f64, unit.in_unit(hour.temp_c?))))
+        .collect();
+    let min = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min).floor() - 1.0;
+    let max = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max).ceil() + 1.0;
+    let hour_label = |hour: Option<&Hour>| {
+        hour.and_then(|h| h.time.as_ref())
+            .and_then(|t| t.split(' ').nth(1))
+            .unwrap_or("")
+            .to_string()
+    };
+    let chart = Chart::new(vec![Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Yellow))
+        .data(&points)])
+    .block(Block::bordered().title(format!("Temperature {}, next 24 hours", unit.symbol())))
+    .x_axis(
+        Axis::default()
+            .bounds([0.0, (hours.len() - 1) as f64])
+            .labels(vec![
+                hour_label(hours.first()),
+                hour_label(hours.get(hours.len() / 2)),
+                hour_label(hours.last()),
+            ]),
+    )
+    .y_axis(
+        Axis::default()
+            .bounds([min, max])
+            .labels(vec![format!("{min}"), format!("{:.0}", (min + max) / 2.0), format!("{max}")]),
+    );
+    frame.render_widget(chart, temperature);
+
+    let bars: Vec<Bar> = hours
+        .iter()
+        .map(|hour| {
+            let chance = hour.chance_of_rain.unwrap_or(0.0).max(hour.chance_of_snow.unwrap_or(0.0));
+            Bar::default()
+                .value(chance as u64)
+                .text_value(String::new())
+                .style(Style::default().fg(if chance >= 50.0 { Color::Blue } else { Color::Cyan }))
+        })
+        .collect();
+    frame.render_widget(
+        BarChart::default()
+            .block(Block::bordered().title("Chance of precipitation %"))
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(1)
+            .bar_gap(0)
+            .max(100),
+        precipitation,
+    );
+}
+
+fn draw_forecast(frame: &mut Frame, state: &State, area: Rect) {
+    let days = state
+        .api
+        .get_cached_forecast()
+        .map(|(response, _)| ForecastDay::list_from_json(response))
+        .unwrap_or_default();
+    let units = &state.units;
+    let rows = days.iter().map(|day| {
+        Row::new(vec![
+            show(&day.date),
+            with_unit(units.temperature(), day.maxtemp_c),
+            with_unit(units.temperature(), day.mintemp_c),
+            format!("{}%", show(&day.daily_chance_of_rain)),
+            with_unit(units.precipitation(), day.totalprecip_mm),
+            show(&day.condition),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(11),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(9),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec!["Date", "High", "Low", "Rain", "Precip", "Condition"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title("Forecast"));
+    frame.render_widget(table, area);
+}
+
+fn draw_alerts(frame: &mut Frame, state: &State, area: Rect) {
+    let alerts = state
+        .api
+        .get_cached_alerts()
+        .map(|(response, _)| Alert::list_from_json(response))
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    if alerts.is_empty() {
+        lines.push(Line::from("No active alerts"));
+    }
+    for alert in &alerts {
+        lines.push(Line::from(Span::styled(
+            show(&alert.headline),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(format!(
+            "{} | {} | until {}",
+            show(&alert.severity),
+            show(&alert.urgency),
+            show(&alert.expires)
+        )));
+        for line in show(&alert.desc).lines() {
+            lines.push(Line::from(line.to_string()));
+        }
+        if let Some(instruction) = &alert.instruction {
+            lines.push(Line::from(Span::styled(
+                instruction.clone(),
+                Style::default().add_modifier(Modifier::ITALIC),
+            )));
+        }
+        lines.push(Line::from(""));
+    }
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((state.alert_scroll, 0))
+            .block(Block::bordered().title(format!("Alerts ({})", alerts.len()))),
+        area,
+    );
+}
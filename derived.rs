@@ -0,0 +1,230 @@
+use crate::facts::Facts;
+
+// Quantities worked out from temperature, humidity and wind, all in the canonical
+// units (°C, %, km/h). Each returns None outside the conditions its formula is for.
+
+// Magnus formula with the Alduchov and Eskridge coefficients
+pub fn dew_point(temp_c: f64, humidity: f64) -> Option<f64> {
+    if humidity <= 0.0 {
+        return None;
+    }
+    let gamma = (humidity / 100.0).ln() + 17.625 * temp_c / (243.04 + temp_c);
+    Some(243.04 * gamma / (17.625 - gamma))
+}
+
+// Water vapour pressure in hPa
+fn vapour_pressure(temp_c: f64, humidity: f64) -> f64 {
+    humidity / 100.0 * 6.105 * (17.27 * temp_c / (237.7 + temp_c)).exp()
+}
+
+// The NWS heat index, Rothfusz's regression with its adjustments. Below 80°F the
+// simpler Steadman fit is used, as the NWS does.
+pub fn heat_index(temp_c: f64, humidity: f64) -> Option<f64> {
+    let t = temp_c * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+    if t < 40.0 {
+        return None;
+    }
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let index = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut index = -42.379 + 2.04901523 * t + 10.14333127 * rh
+            - 0.22475541 * t * rh
+            - 0.00683783 * t * t
+            - 0.05481717 * rh * rh
+            + 0.00122874 * t * t * rh
+            + 0.00085282 * t * rh * rh
+            - 0.00000199 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+        index
+    };
+    Some((index - 32.0) * 5.0 / 9.0)
+}
+
+// Environment Canada's humidex, unitless but read like °C
+pub fn humidex(temp_c: f64, dew_point_c: f64) -> f64 {
+    let e = 6.11 * (5417.7530 * (1.0 / 273.16 - 1.0 / (273.15 + dew_point_c))).exp();
+    temp_c + 0.5555 * (e - 10.0)
+}
+
+// The 2001 North American wind chill, only defined at or below 10°C in wind over 4.8 km/h
+pub fn wind_chill(temp_c: f64, wind_kph: f64) -> Option<f64> {
+    if temp_c > 10.0 || wind_kph <= 4.8 {
+        return None;
+    }
+    let v = wind_kph.powf(0.16);
+    Some(13.12 + 0.6215 * temp_c - 11.37 * v + 0.3965 * temp_c * v)
+}
+
+// Stull's 2011 fit, good to about 1°C between 5% and 99% humidity
+pub fn wet_bulb(temp_c: f64, humidity: f64) -> Option<f64> {
+    if !(5.0..=99.0).contains(&humidity) {
+        return None;
+    }
+    let rh = humidity;
+    Some(
+        temp_c * (0.151977 * (rh + 8.313659).sqrt()).atan() + (temp_c + rh).atan() - (rh - 1.676331).atan()
+            + 0.00391838 * rh.powf(1.5) * (0.023101 * rh).atan()
+            - 4.686035,
+    )
+}
+
+// Steadman's apparent temperature as the Australian Bureau of Meteorology uses it,
+// without the radiation term
+pub fn apparent_temperature(temp_c: f64, humidity: f64, wind_kph: f64) -> f64 {
+    temp_c + 0.33 * vapour_pressure(temp_c, humidity) - 0.70 * wind_kph / 3.6 - 4.00
+}
+
+// Height of cumulus bases above the ground in metres, from the spread between
+// temperature and dew point
+pub fn cloud_base(temp_c: f64, dew_point_c: f64) -> f64 {
+    (temp_c - dew_point_c).max(0.0) * 125.0
+}
+
+// Grams of water vapour per m³ of air
+pub fn absolute_humidity(temp_c: f64, humidity: f64) -> f64 {
+    6.112 * (17.67 * temp_c / (temp_c + 243.5)).exp() * humidity * 2.1674 / (273.15 + temp_c)
+}
+
+// Upper limits of each force in km/h, force 12 is anything above the last
+const BEAUFORT_LIMITS: [f64; 12] = [2.0, 6.0, 12.0, 20.0, 29.0, 39.0, 50.0, 62.0, 75.0, 89.0, 103.0, 118.0];
+
+const BEAUFORT_NAMES: [&str; 13] = [
+    "Calm",
+    "Light air",
+    "Light breeze",
+    "Gentle breeze",
+    "Moderate breeze",
+    "Fresh breeze",
+    "Strong breeze",
+    "Near gale",
+    "Gale",
+    "Strong gale",
+    "Storm",
+    "Violent storm",
+    "Hurricane force",
+];
+
+pub fn beaufort(wind_kph: f64) -> usize {
+    BEAUFORT_LIMITS
+        .iter()
+        .position(|limit| wind_kph < *limit)
+        .unwrap_or(BEAUFORT_LIMITS.len())
+}
+
+pub fn beaufort_name(force: usize) -> &'static str {
+    BEAUFORT_NAMES[force.min(12)]
+}
+
+// Hurricane category for sustained wind, 0 below hurricane strength
+pub fn saffir_simpson(wind_kph: f64) -> usize {
+    [119.0, 154.0, 178.0, 209.0, 252.0]
+        .iter()
+        .filter(|limit| wind_kph >= **limit)
+        .count()
+}
+
+// Adds everything that can be worked out from the facts already there. Values the
+// provider sent are kept, with {name}_difference saying how far off the local
+// calculation is, so a provider that leaves them out still gets them.
+pub fn add_facts(facts: &mut Facts) {
+    let (Some(temp), Some(humidity)) = (facts.number("temp_c"), facts.number("humidity")) else {
+        return;
+    };
+    let wind = facts.number("wind_kph");
+
+    let mut fill = |name: &str, value: Option<f64>| {
+        let Some(value) = value else {
+            return;
+        };
+        match facts.number(name) {
+            Some(provided) => facts.insert_number(&format!("{name}_difference"), provided - value),
+            None => facts.insert_number(name, value),
+        }
+    };
+    let dew_point = dew_point(temp, humidity);
+    fill("dewpoint_c", dew_point);
+    fill("heatindex_c", heat_index(temp, humidity));
+    fill("windchill_c", wind.and_then(|wind| wind_chill(temp, wind)));
+    fill("wetbulb_c", wet_bulb(temp, humidity));
+    fill("absolute_humidity_g_m3", Some(absolute_humidity(temp, humidity)));
+    if let Some(dew_point) = dew_point {
+        fill("humidex", Some(humidex(temp, dew_point)));
+        fill("cloud_base_m", Some(cloud_base(temp, dew_point)));
+    }
+    if let Some(wind) = wind {
+        fill("apparent_temp_c", Some(apparent_temperature(temp, humidity, wind)));
+        fill("beaufort", Some(beaufort(wind) as f64));
+        fill("saffir_simpson", Some(saffir_simpson(wind) as f64));
+        facts.insert_text("beaufort_name", beaufort_name(beaufort(wind)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(value: f64, expected: f64, tolerance: f64) {
+        assert!((value - expected).abs() <= tolerance, "{value} is not within {tolerance} of {expected}");
+    }
+
+    // Reference values are from the NWS heat index and Environment Canada wind chill
+    // and humidex tables, and the examples in Stull's paper
+    #[test]
+    fn reference_values() {
+        near(dew_point(20.0, 50.0).unwrap(), 9.3, 0.1);
+        near(dew_point(30.0, 100.0).unwrap(), 30.0, 0.01);
+        // 90°F at 70% is 106°F in the NWS table
+        near(heat_index(32.2, 70.0).unwrap(), 41.1, 0.5);
+        near(heat_index(32.0, 70.0).unwrap(), 40.6, 0.5);
+        near(heat_index(20.0, 50.0).unwrap(), 19.5, 0.5);
+        near(humidex(30.0, 15.0), 34.0, 0.5);
+        near(wind_chill(-10.0, 30.0).unwrap(), -19.5, 0.1);
+        near(wind_chill(0.0, 10.0).unwrap(), -3.3, 0.1);
+        near(wet_bulb(20.0, 50.0).unwrap(), 13.7, 0.1);
+        near(apparent_temperature(30.0, 50.0, 10.0), 31.0, 0.2);
+        near(cloud_base(20.0, 10.0), 1250.0, 0.01);
+        near(absolute_humidity(20.0, 100.0), 17.3, 0.1);
+    }
+
+    #[test]
+    fn outside_the_formulas() {
+        assert!(dew_point(20.0, 0.0).is_none());
+        assert!(dew_point(20.0, 0.1).is_some());
+        assert!(heat_index(4.0, 50.0).is_none());
+        assert!(heat_index(4.5, 50.0).is_some());
+        assert!(wind_chill(10.1, 20.0).is_none());
+        assert!(wind_chill(10.0, 20.0).is_some());
+        assert!(wind_chill(0.0, 4.8).is_none());
+        assert!(wind_chill(0.0, 4.9).is_some());
+        assert!(wet_bulb(20.0, 4.9).is_none());
+        assert!(wet_bulb(20.0, 5.0).is_some());
+        assert!(wet_bulb(20.0, 99.0).is_some());
+        assert!(wet_bulb(20.0, 99.1).is_none());
+        assert_eq!(cloud_base(10.0, 12.0), 0.0);
+    }
+
+    #[test]
+    fn wind_classes() {
+        assert_eq!(beaufort(0.0), 0);
+        assert_eq!(beaufort(1.9), 0);
+        assert_eq!(beaufort(2.0), 1);
+        assert_eq!(beaufort(40.0), 6);
+        assert_eq!(beaufort(117.9), 11);
+        assert_eq!(beaufort(118.0), 12);
+        assert_eq!(beaufort_name(beaufort(40.0)), "Strong breeze");
+        assert_eq!(beaufort_name(20), "Hurricane force");
+        assert_eq!(saffir_simpson(118.9), 0);
+        assert_eq!(saffir_simpson(119.0), 1);
+        assert_eq!(saffir_simpson(153.9), 1);
+        assert_eq!(saffir_simpson(154.0), 2);
+        assert_eq!(saffir_simpson(178.0), 3);
+        assert_eq!(saffir_simpson(209.0), 4);
+        assert_eq!(saffir_simpson(252.0), 5);
+    }
+}
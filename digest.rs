@@ -0,0 +1,229 @@
+use crate::api::Api;
+use crate::history::Observation;
+use crate::models::{Alert, ForecastDay, Hour};
+use crate::stats;
+use crate::units::{with_unit, DisplayUnits};
+use crate::utils::Schedule;
+use chrono::{DateTime, Days, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+
+// Where digests are sent. Command runs a program with the title and body as its last
+// two arguments, e.g. Command(["ntfy", "publish", "weather"])
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Channel {
+    Desktop,
+    Log,
+    Command(Vec<String>),
+}
+
+#[derive(Debug)]
+pub enum DeliveryError {
+    Desktop(notify_rust::error::Error),
+    Command(std::io::Error),
+    CommandFailed(std::process::ExitStatus),
+    EmptyCommand,
+}
+
+impl std::fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Desktop(e) => write!(f, "desktop notification failed: {e}"),
+            Self::Command(e) => write!(f, "could not run command: {e}"),
+            Self::CommandFailed(status) => write!(f, "command exited with {status}"),
+            Self::EmptyCommand => write!(f, "no command given"),
+        }
+    }
+}
+
+// Summaries sent on a schedule of local times, e.g.
+// digest: (schedule: "07:00,17:30", channels: [Desktop, Log])
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DigestConfiguration {
+    // Older configs call this times and give a list of times of day
+    #[serde(alias = "times")]
+    pub schedule: Schedule,
+    pub channels: Vec<Channel>,
+    // Chance of rain or snow in % for an hour to count as wet in the timeline
+    pub precipitation_chance: f64,
+    // How much warmer or colder than yesterday is worth mentioning, in °C
+    pub notable_change_c: f64,
+}
+
+impl Default for DigestConfiguration {
+    fn default() -> Self {
+        Self {
+            schedule: Schedule::Never,
+            channels: vec![Channel::Desktop],
+            precipitation_chance: 50.0,
+            notable_change_c: 3.0,
+        }
+    }
+}
+
+impl DigestConfiguration {
+    pub fn title(&self, now: DateTime<Local>) -> &'static str {
+        if now.hour() < 12 {
+            "Morning Digest"
+        } else {
+            "Evening Digest"
+        }
+    }
+}
+
+fn day_line(label: &str, day: Option<&ForecastDay>, units: &DisplayUnits) -> Option<String> {
+    let day = day?;
+    let temperature = units.temperature();
+    Some(format!(
+        "{label}: high {}, low {}, {}",
+        with_unit(temperature, day.maxtemp_c),
+        with_unit(temperature, day.mintemp_c),
+        day.condition.as_deref().unwrap_or("-")
+    ))
+}
+
+fn clock(hour: &Hour) -> &str {
+    hour.time.as_deref().and_then(|time| time.get(11..)).unwrap_or("?")
+}
+
+// Runs of wet hours as "Rain 13:00-16:00 (up to 80%)"
+fn precipitation_timeline(hours: &[Hour], threshold: f64) -> Vec<String> {
+    let chance = |hour: &Hour| {
+        let (rain, snow) = (hour.chance_of_rain.unwrap_or(0.0), hour.chance_of_snow.unwrap_or(0.0));
+        (rain.max(snow), if snow > rain { "Snow" } else { "Rain" })
+    };
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < hours.len() {
+        let (first, kind) = chance(&hours[i]);
+        if first < threshold {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut peak = first;
+        while i < hours.len() && chance(&hours[i]).0 >= threshold {
+            peak = peak.max(chance(&hours[i]).0);
+            i += 1;
+        }
+        let end = match hours.get(i) {
+            Some(hour) => clock(hour).to_string(),
+            None => String::from("later"),
+        };
+        spans.push(format!("{kind} {}-{end} (up to {peak}%)", clock(&hours[start])));
+    }
+    spans
+}
+
+// How today's forecast compares with what was recorded yesterday
+fn changes(today: &ForecastDay, observations: &[Observation], threshold: f64, units: &DisplayUnits) -> Vec<String> {
+    let Some(date) = today.date.as_deref().and_then(|date| date.parse::<NaiveDate>().ok()) else {
+        return Vec::new();
+    };
+    let days = stats::daily(observations);
+    let Some(yesterday) = days.iter().find(|day| day.date + Days::new(1) == date) else {
+        return Vec::new();
+    };
+    let temperature = units.temperature();
+    let mut changes = Vec::new();
+    if let Some(high) = today.maxtemp_c {
+        let change = high - yesterday.max_c;
+        if change.abs() >= threshold {
+            changes.push(format!(
+                "{} than yesterday, high {} after {}",
+                if change > 0.0 { "Warmer" } else { "Colder" },
+                with_unit(temperature, Some(high)),
+                with_unit(temperature, Some(yesterday.max_c))
+            ));
+        }
+    }
+    if let Some(low) = today.mintemp_c {
+        if low < 0.0 && yesterday.min_c >= 0.0 {
+            changes.push(format!(
+                "Frost after a mild night, low {} after {}",
+                with_unit(temperature, Some(low)),
+                with_unit(temperature, Some(yesterday.min_c))
+            ));
+        }
+    }
+    if let Some(rain) = today.totalprecip_mm {
+        let precipitation = units.precipitation();
+        if rain >= 1.0 && yesterday.rain_mm < 1.0 {
+            changes.push(format!("Wet after a dry day, {} expected", with_unit(precipitation, Some(rain))));
+        } else if rain < 1.0 && yesterday.rain_mm >= 1.0 {
+            changes.push(format!("Drier after {} yesterday", with_unit(precipitation, Some(yesterday.rain_mm))));
+        }
+    }
+    changes
+}
+
+// The digest for one location from its cached responses, None without a forecast
+pub fn compose(api: &Api, observations: &[Observation], config: &DigestConfiguration, units: &DisplayUnits) -> Option<String> {
+    let (forecast, _timestamp) = api.get_cached_forecast()?;
+    let days = ForecastDay::list_from_json(forecast);
+    if days.is_empty() {
+        return None;
+    }
+    let mut lines: Vec<String> = [("Today", days.first()), ("Tomorrow", days.get(1))]
+        .into_iter()
+        .filter_map(|(label, day)| day_line(label, day, units))
+        .collect();
+
+    let timeline = precipitation_timeline(&Hour::upcoming(forecast, 24), config.precipitation_chance);
+    if timeline.is_empty() {
+        lines.push(String::from("No rain or snow likely in the next 24 hours"));
+    }
+    lines.extend(timeline);
+
+    if let Some(today) = days.first() {
+        lines.extend(changes(today, observations, config.notable_change_c, units));
+    }
+
+    if let Some((response, _timestamp)) = api.get_cached_alerts() {
+        for alert in Alert::list_from_json(response) {
+            lines.push(format!("Alert: {}", alert.headline.as_deref().unwrap_or("-")));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+pub fn deliver(channel: &Channel, title: &str, body: &str) -> Result<(), DeliveryError> {
+    match channel {
+        Channel::Desktop => notify_rust::Notification::new()
+            .summary(title)
+            .body(body)
+            .show()
+            .map(|_| ())
+            .map_err(DeliveryError::Desktop),
+        Channel::Log => {
+            println!("{}\n{title}\n{body}", Local::now());
+            Ok(())
+        }
+        Channel::Command(command) => {
+            let Some((program, args)) = command.split_first() else {
+                return Err(DeliveryError::EmptyCommand);
+            };
+            let status = std::process::Command::new(program)
+                .args(args)
+                .args([title, body])
+                .status()
+                .map_err(DeliveryError::Command)?;
+            match status.success() {
+                true => Ok(()),
+                false => Err(DeliveryError::CommandFailed(status)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_times_still_schedule_digests() {
+        let config: DigestConfiguration = ron::from_str(r#"(times: ["07:00:00", "17:30:00"], channels: [Log])"#).unwrap();
+        assert_eq!(config.schedule.to_string(), "07:00,17:30");
+        assert_eq!(config.channels, vec![Channel::Log]);
+    }
+}
@@ -0,0 +1,782 @@
+use crate::facts::Fact;
+use crate::history::{self, ForecastRecord, MarineRecord, Observation};
+use crate::units::{Height, Precipitation, Pressure, Speed, Temperature, Unit};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::Path,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Dataset {
+    Observations,
+    Forecasts,
+    Marine,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    Csv,
+    Jsonl,
+    Ron,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+#[derive(Debug)]
+pub enum TransferError {
+    UnknownColumn(String),
+    FailedToWrite,
+    FailedToRecord,
+    ParseError { line: usize, reason: String },
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownColumn(name) => write!(f, "unknown column {name}"),
+            Self::FailedToWrite => write!(f, "failed to write output"),
+            Self::FailedToRecord => write!(f, "failed to add records to history"),
+            Self::ParseError { line, reason } => write!(f, "line {line}: {reason}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Quantity {
+    Plain,
+    Temperature,
+    Pressure,
+    Precipitation,
+    Speed,
+    Height,
+}
+
+// Columns are named after the WeatherAPI fields they came from. Measurements get a
+// unit suffix (temp_c, temp_f, ...) in the header, but can be selected without it.
+struct Column {
+    key: &'static str,
+    quantity: Quantity,
+}
+
+const OBSERVATION_COLUMNS: &[Column] = &[
+    Column { key: "provider", quantity: Quantity::Plain },
+    Column { key: "location", quantity: Quantity::Plain },
+    Column { key: "time", quantity: Quantity::Plain },
+    Column { key: "local_time", quantity: Quantity::Plain },
+    Column { key: "temp", quantity: Quantity::Temperature },
+    Column { key: "feelslike", quantity: Quantity::Temperature },
+    Column { key: "humidity", quantity: Quantity::Plain },
+    Column { key: "pressure", quantity: Quantity::Pressure },
+    Column { key: "precip", quantity: Quantity::Precipitation },
+    Column { key: "wind", quantity: Quantity::Speed },
+    Column { key: "condition", quantity: Quantity::Plain },
+];
+
+const FORECAST_COLUMNS: &[Column] = &[
+    Column { key: "provider", quantity: Quantity::Plain },
+    Column { key: "location", quantity: Quantity::Plain },
+    Column { key: "issued", quantity: Quantity::Plain },
+    Column { key: "date", quantity: Quantity::Plain },
+    Column { key: "lead_days", quantity: Quantity::Plain },
+    Column { key: "maxtemp", quantity: Quantity::Temperature },
+    Column { key: "mintemp", quantity: Quantity::Temperature },
+    Column { key: "avgtemp", quantity: Quantity::Temperature },
+    Column { key: "totalprecip", quantity: Quantity::Precipitation },
+    Column { key: "chance_of_precip", quantity: Quantity::Plain },
+    Column { key: "will_precip", quantity: Quantity::Plain },
+];
+
+const MARINE_COLUMNS: &[Column] = &[
+    Column { key: "provider", quantity: Quantity::Plain },
+    Column { key: "location", quantity: Quantity::Plain },
+    Column { key: "time", quantity: Quantity::Plain },
+    Column { key: "local_time", quantity: Quantity::Plain },
+    Column { key: "sig_ht", quantity: Quantity::Height },
+    Column { key: "swell_ht", quantity: Quantity::Height },
+    Column { key: "swell_dir", quantity: Quantity::Plain },
+    Column { key: "swell_period_secs", quantity: Quantity::Plain },
+    Column { key: "water_temp", quantity: Quantity::Temperature },
+];
+
+impl Quantity {
+    fn suffix(self, units: Units) -> &'static str {
+        match (self, units) {
+            (Self::Plain, _) => "",
+            (Self::Temperature, Units::Metric) => "_c",
+            (Self::Temperature, Units::Imperial) => "_f",
+            (Self::Pressure, Units::Metric) => "_mb",
+            (Self::Pressure, Units::Imperial) => "_in",
+            (Self::Precipitation, Units::Metric) => "_mm",
+            (Self::Precipitation, Units::Imperial) => "_in",
+            (Self::Speed, Units::Metric) => "_kph",
+            (Self::Speed, Units::Imperial) => "_mph",
+            (Self::Height, Units::Metric) => "_mt",
+            (Self::Height, Units::Imperial) => "_ft",
+        }
+    }
+
+    fn in_units(self, value: f64, units: Units) -> f64 {
+        match (self, units) {
+            (_, Units::Metric) | (Self::Plain, _) => value,
+            (Self::Temperature, Units::Imperial) => Temperature::Fahrenheit.in_unit(value),
+            (Self::Pressure, Units::Imperial) => Pressure::InchesOfMercury.in_unit(value),
+            (Self::Precipitation, Units::Imperial) => Precipitation::Inches.in_unit(value),
+            (Self::Speed, Units::Imperial) => Speed::Mph.in_unit(value),
+            (Self::Height, Units::Imperial) => Height::Feet.in_unit(value),
+        }
+    }
+
+    fn to_metric(self, value: f64, units: Units) -> f64 {
+        match (self, units) {
+            (_, Units::Metric) | (Self::Plain, _) => value,
+            (Self::Temperature, Units::Imperial) => Temperature::Fahrenheit.to_canonical(value),
+            (Self::Pressure, Units::Imperial) => Pressure::InchesOfMercury.to_canonical(value),
+            (Self::Precipitation, Units::Imperial) => Precipitation::Inches.to_canonical(value),
+            (Self::Speed, Units::Imperial) => Speed::Mph.to_canonical(value),
+            (Self::Height, Units::Imperial) => Height::Feet.to_canonical(value),
+        }
+    }
+}
+
+impl Dataset {
+    fn columns(self) -> &'static [Column] {
+        match self {
+            Self::Observations => OBSERVATION_COLUMNS,
+            Self::Forecasts => FORECAST_COLUMNS,
+            Self::Marine => MARINE_COLUMNS,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub dataset: Dataset,
+    pub format: Format,
+    pub columns: Option<Vec<String>>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub location: Option<String>,
+    pub units: Units,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub dataset: Dataset,
+    pub format: Format,
+    pub units: Units,
+    pub location: Option<String>,
+    pub provider: String,
+    // (column in the file, column it should be read as)
+    pub mappings: Vec<(String, String)>,
+}
+
+fn text(value: &str) -> Fact {
+    Fact::Text(value.to_string())
+}
+
+// Missing measurements are left empty
+fn measurement(value: Option<f64>) -> Fact {
+    value.map_or(text(""), Fact::Number)
+}
+
+fn observation_values(observation: &Observation) -> Vec<Fact> {
+    vec![
+        text(&observation.provider),
+        text(&observation.location),
+        Fact::Number(observation.time as f64),
+        text(&observation.local_time.format("%Y-%m-%d %H:%M").to_string()),
+        Fact::Number(observation.temp_c),
+        Fact::Number(observation.feelslike_c),
+        measurement(observation.humidity),
+        measurement(observation.pressure_mb),
+        Fact::Number(observation.precip_mm),
+        measurement(observation.wind_kph),
+        text(&observation.condition),
+    ]
+}
+
+fn forecast_values(forecast: &ForecastRecord) -> Vec<Fact> {
+    vec![
+        text(&forecast.provider),
+        text(&forecast.location),
+        Fact::Number(forecast.issued as f64),
+        text(&forecast.date.format("%Y-%m-%d").to_string()),
+        Fact::Number(forecast.lead_days as f64),
+        Fact::Number(forecast.maxtemp_c),
+        Fact::Number(forecast.mintemp_c),
+        Fact::Number(forecast.avgtemp_c),
+        Fact::Number(forecast.totalprecip_mm),
+        Fact::Number(forecast.chance_of_precip),
+        Fact::Number(if forecast.will_precip { 1.0 } else { 0.0 }),
+    ]
+}
+
+fn marine_values(record: &MarineRecord) -> Vec<Fact> {
+    vec![
+        text(&record.provider),
+        text(&record.location),
+        Fact::Number(record.time as f64),
+        text(&record.local_time.format("%Y-%m-%d %H:%M").to_string()),
+        measurement(record.sig_ht_mt),
+        measurement(record.swell_ht_mt),
+        measurement(record.swell_dir),
+        measurement(record.swell_period_secs),
+        measurement(record.water_temp_c),
+    ]
+}
+
+fn in_range(date: NaiveDate, options: &ExportOptions) -> bool {
+    options.from.is_none_or(|from| date >= from) && options.to.is_none_or(|to| date <= to)
+}
+
+fn selected_columns(options: &ExportOptions) -> Result<Vec<usize>, TransferError> {
+    let columns = options.dataset.columns();
+    let Some(selection) = &options.columns else {
+        return Ok((0..columns.len()).collect());
+    };
+    selection
+        .iter()
+        .map(|name| {
+            columns
+                .iter()
+                .position(|column| {
+                    column.key == name
+                        || format!("{}{}", column.key, column.quantity.suffix(options.units)) == *name
+                })
+                .ok_or_else(|| TransferError::UnknownColumn(name.clone()))
+        })
+        .collect()
+}
+
+pub fn export(
+    working_directory: &Path,
+    options: &ExportOptions,
+    out: &mut impl Write,
+) -> Result<usize, TransferError> {
+    let columns = options.dataset.columns();
+    let selected = selected_columns(options)?;
+    let location_matches =
+        |location: &str| options.location.as_ref().is_none_or(|wanted| wanted == location);
+
+    let rows: Vec<Vec<Fact>> = match options.dataset {
+        Dataset::Observations => history::load_observations(working_directory)
+            .iter()
+            .filter(|o| location_matches(&o.location) && in_range(o.local_time.date(), options))
+            .map(observation_values)
+            .collect(),
+        Dataset::Forecasts => history::load_forecasts(working_directory)
+            .iter()
+            .filter(|f| location_matches(&f.location) && in_range(f.date, options))
+            .map(forecast_values)
+            .collect(),
+        Dataset::Marine => history::load_marine(working_directory)
+            .iter()
+            .filter(|r| location_matches(&r.location) && in_range(r.local_time.date(), options))
+            .map(marine_values)
+            .collect(),
+    };
+
+    let header: Vec<String> = selected
+        .iter()
+        .map(|&i| format!("{}{}", columns[i].key, columns[i].quantity.suffix(options.units)))
+        .collect();
+    let rows = rows.into_iter().map(|row| {
+        selected
+            .iter()
+            .map(|&i| match &row[i] {
+                Fact::Number(value) if options.units != Units::Metric => {
+                    let value = columns[i].quantity.in_units(*value, options.units);
+                    Fact::Number((value * 100.0).round() / 100.0)
+                }
+                other => other.clone(),
+            })
+            .collect::<Vec<Fact>>()
+    });
+
+    let mut count = 0;
+    let mut write = |line: String| writeln!(out, "{line}").map_err(|_| TransferError::FailedToWrite);
+    if let Format::Csv = options.format {
+        write(header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(","))?;
+    }
+    for row in rows {
+        count += 1;
+        match options.format {
+            Format::Csv => write(
+                row.iter()
+                    .map(|value| csv_field(&value.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )?,
+            Format::Jsonl => {
+                let mut object = json::JsonValue::new_object();
+                for (name, value) in header.iter().zip(row) {
+                    object[name.as_str()] = match value {
+                        Fact::Number(value) => value.into(),
+                        Fact::Text(text) if text.is_empty() => json::JsonValue::Null,
+                        Fact::Text(text) => text.into(),
+                    };
+                }
+                write(object.dump())?
+            }
+            Format::Ron => {
+                let map: BTreeMap<&String, Fact> = header.iter().zip(row).collect();
+                write(ron::to_string(&map).map_err(|_| TransferError::FailedToWrite)?)?
+            }
+        }
+    }
+    Ok(count)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Splits CSV into records, with the line each one starts on. Quoted fields can hold
+// commas, line breaks and quotes written twice.
+fn csv_records(input: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let (mut line, mut start) = (1, 1);
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                fields.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut fields)));
+                line += 1;
+                start = line;
+            }
+            (c, _) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start, fields));
+    }
+    records.retain(|(_, fields)| fields.len() > 1 || fields.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+fn csv_value(field: &str) -> Fact {
+    match field.trim().parse::<f64>() {
+        Ok(value) => Fact::Number(value),
+        Err(_) => text(field.trim()),
+    }
+}
+
+// Reads every record in the input as a map from column name to value
+fn parse_rows(input: &str, format: Format) -> Result<Vec<BTreeMap<String, Fact>>, TransferError> {
+    let lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    match format {
+        Format::Csv => {
+            let mut records = csv_records(input.trim_start_matches('\u{feff}')).into_iter();
+            let Some((_, header)) = records.next() else {
+                return Ok(Vec::new());
+            };
+            records
+                .map(|(line, fields)| {
+                    if fields.len() != header.len() {
+                        return Err(TransferError::ParseError {
+                            line,
+                            reason: format!(
+                                "expected {} fields, found {}",
+                                header.len(),
+                                fields.len()
+                            ),
+                        });
+                    }
+                    Ok(header
+                        .iter()
+                        .map(|h| h.trim().to_lowercase())
+                        .zip(fields.iter().map(|f| csv_value(f)))
+                        .filter(|(_, value)| !matches!(value, Fact::Text(t) if t.is_empty()))
+                        .collect())
+                })
+                .collect()
+        }
+        Format::Jsonl => lines
+            .map(|(i, line)| {
+                let object = json::parse(line).map_err(|e| TransferError::ParseError {
+                    line: i + 1,
+                    reason: e.to_string(),
+                })?;
+                Ok(object
+                    .entries()
+                    .filter_map(|(name, value)| {
+                        let value = if let Some(number) = value.as_f64() {
+                            Fact::Number(number)
+                        } else if let Some(flag) = value.as_bool() {
+                            Fact::Number(if flag { 1.0 } else { 0.0 })
+                        } else {
+                            text(value.as_str()?)
+                        };
+                        Some((name.to_lowercase(), value))
+                    })
+                    .collect())
+            })
+            .collect(),
+        Format::Ron => lines
+            .map(|(i, line)| {
+                ron::from_str::<BTreeMap<String, Fact>>(line)
+                    .map(|row| row.into_iter().map(|(k, v)| (k.to_lowercase(), v)).collect())
+                    .map_err(|e| TransferError::ParseError {
+                        line: i + 1,
+                        reason: e.to_string(),
+                    })
+            })
+            .collect(),
+    }
+}
+
+// Other stations name their columns differently. Common spellings are recognised,
+// and a unit suffix in the name wins over the --units option.
+const ALIASES: &[(&str, &[&str])] = &[
+    ("time", &["epoch", "timestamp", "time_epoch", "last_updated_epoch"]),
+    ("local_time", &["datetime", "date_time", "observation_time", "last_updated", "localtime"]),
+    ("temp", &["temperature", "air_temp", "tempc", "tempf"]),
+    ("feelslike", &["feels_like", "apparent_temp", "apparent_temperature"]),
+    ("humidity", &["rh", "relative_humidity", "humidity_pct"]),
+    ("pressure", &["barometer", "pressure_sea_level", "slp", "baromrelin", "baromin"]),
+    ("precip", &["rain", "precipitation", "rain_1h", "hourlyrainin"]),
+    ("wind", &["wind_speed", "windspeed", "windspeedmph"]),
+    ("condition", &["weather", "conditions", "summary"]),
+    ("maxtemp", &["max_temp", "high", "temp_max"]),
+    ("mintemp", &["min_temp", "low", "temp_min"]),
+    ("avgtemp", &["avg_temp", "mean_temp", "temp_mean"]),
+    ("totalprecip", &["total_precip", "precip_total"]),
+    ("sig_ht", &["wave_height", "significant_wave_height", "wvht"]),
+    ("swell_dir", &["swell_direction", "mwd"]),
+    ("swell_period_secs", &["swell_period", "dpd"]),
+    ("water_temp", &["sea_temp", "sea_surface_temperature", "wtmp"]),
+];
+
+fn resolve(
+    row: &BTreeMap<String, Fact>,
+    column: &Column,
+    options: &ImportOptions,
+) -> Option<Fact> {
+    let mapped = |name: &str| -> String {
+        options
+            .mappings
+            .iter()
+            .find(|(_, to)| to == name)
+            .map(|(from, _)| from.to_lowercase())
+            .unwrap_or(name.to_string())
+    };
+
+    let unit_variants = [(Units::Metric, column.quantity.suffix(Units::Metric)), (Units::Imperial, column.quantity.suffix(Units::Imperial))];
+    let mut candidates: Vec<(String, Option<Units>)> = vec![(mapped(column.key), None)];
+    for (units, suffix) in unit_variants {
+        if !suffix.is_empty() {
+            candidates.push((mapped(&format!("{}{}", column.key, suffix)), Some(units)));
+        }
+    }
+    if let Some((_, aliases)) = ALIASES.iter().find(|(key, _)| *key == column.key) {
+        candidates.extend(aliases.iter().map(|alias| (alias.to_string(), None)));
+    }
+
+    candidates.into_iter().find_map(|(name, units)| match row.get(&name)? {
+        Fact::Number(value) => Some(Fact::Number(
+            column
+                .quantity
+                .to_metric(*value, units.unwrap_or(options.units)),
+        )),
+        Fact::Text(value) => Some(text(value)),
+    })
+}
+
+// The epoch and, when the value says it, the local time
+fn parse_time(value: &Fact) -> Option<(i64, Option<NaiveDateTime>)> {
+    match value {
+        Fact::Number(epoch) => {
+            let time = DateTime::from_timestamp(*epoch as i64, 0)?;
+            Some((time.timestamp(), None))
+        }
+        Fact::Text(text) => {
+            if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+                return Some((time.timestamp(), Some(time.naive_local())));
+            }
+            // Without an offset the time is assumed to be the station's local time
+            // and the epoch is approximated as if that were UTC
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y/%m/%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+                .map(|time| (time.and_utc().timestamp(), Some(time)))
+        }
+    }
+}
+
+// The UTC offset of the latest record for each location, from its epoch and local time
+fn offsets<'a>(records: impl Iterator<Item = (&'a str, i64, NaiveDateTime)>) -> HashMap<String, FixedOffset> {
+    records
+        .filter_map(|(location, time, local_time)| {
+            let utc = DateTime::from_timestamp(time, 0)?.naive_utc();
+            let offset = FixedOffset::east_opt((local_time - utc).num_seconds().try_into().ok()?)?;
+            Some((location.to_string(), offset))
+        })
+        .collect()
+}
+
+// The epoch and local time of a row. Rows with only an epoch are given the offset
+// already recorded for their location, or the machine's when there is none.
+fn timestamps(
+    row: &BTreeMap<String, Fact>,
+    columns: &[Column],
+    options: &ImportOptions,
+    location: &str,
+    offsets: &HashMap<String, FixedOffset>,
+) -> Option<(i64, NaiveDateTime)> {
+    let epoch = value(row, "time", columns, options).and_then(|time| parse_time(&time));
+    let local = value(row, "local_time", columns, options).and_then(|time| parse_time(&time));
+    match (epoch, local) {
+        (Some((epoch, _)), Some((_, Some(local)))) | (None, Some((epoch, Some(local)))) => Some((epoch, local)),
+        (Some((epoch, _)), _) | (None, Some((epoch, None))) => {
+            let time = DateTime::from_timestamp(epoch, 0)?;
+            let local = match offsets.get(location) {
+                Some(offset) => time.with_timezone(offset).naive_local(),
+                None => time.with_timezone(&Local).naive_local(),
+            };
+            Some((epoch, local))
+        }
+        (None, None) => None,
+    }
+}
+
+fn value(
+    row: &BTreeMap<String, Fact>,
+    key: &str,
+    columns: &[Column],
+    options: &ImportOptions,
+) -> Option<Fact> {
+    resolve(row, columns.iter().find(|c| c.key == key)?, options)
+}
+
+fn number(row: &BTreeMap<String, Fact>, key: &str, columns: &[Column], options: &ImportOptions) -> Option<f64> {
+    match value(row, key, columns, options)? {
+        Fact::Number(value) => Some(value),
+        Fact::Text(text) => text.parse().ok(),
+    }
+}
+
+fn string(row: &BTreeMap<String, Fact>, key: &str, columns: &[Column], options: &ImportOptions) -> Option<String> {
+    value(row, key, columns, options).map(|value| value.to_string())
+}
+
+fn observation_from_row(
+    row: &BTreeMap<String, Fact>,
+    options: &ImportOptions,
+    offsets: &HashMap<String, FixedOffset>,
+) -> Option<Observation> {
+    let columns = OBSERVATION_COLUMNS;
+    let location = options
+        .location
+        .clone()
+        .or_else(|| string(row, "location", columns, options))?;
+    let (time, local_time) = timestamps(row, columns, options, &location, offsets)?;
+    let temp_c = number(row, "temp", columns, options)?;
+
+    Some(Observation {
+        provider: string(row, "provider", columns, options).unwrap_or(options.provider.clone()),
+        location,
+        time,
+        local_time,
+        temp_c,
+        feelslike_c: number(row, "feelslike", columns, options).unwrap_or(temp_c),
+        humidity: number(row, "humidity", columns, options),
+        pressure_mb: number(row, "pressure", columns, options),
+        precip_mm: number(row, "precip", columns, options).unwrap_or(0.0),
+        wind_kph: number(row, "wind", columns, options),
+        condition: string(row, "condition", columns, options).unwrap_or_default(),
+    })
+}
+
+fn forecast_from_row(row: &BTreeMap<String, Fact>, options: &ImportOptions) -> Option<ForecastRecord> {
+    let columns = FORECAST_COLUMNS;
+    let date = NaiveDate::parse_from_str(&string(row, "date", columns, options)?, "%Y-%m-%d").ok()?;
+    let issued = number(row, "issued", columns, options)? as i64;
+    let maxtemp_c = number(row, "maxtemp", columns, options)?;
+    let mintemp_c = number(row, "mintemp", columns, options)?;
+    let chance_of_precip = number(row, "chance_of_precip", columns, options).unwrap_or(0.0);
+
+    Some(ForecastRecord {
+        provider: string(row, "provider", columns, options).unwrap_or(options.provider.clone()),
+        location: options
+            .location
+            .clone()
+            .or_else(|| string(row, "location", columns, options))?,
+        issued,
+        date,
+        lead_days: number(row, "lead_days", columns, options).map_or_else(
+            || (date - DateTime::from_timestamp(issued, 0).unwrap_or_default().date_naive()).num_days(),
+            |lead| lead as i64,
+        ),
+        maxtemp_c,
+        mintemp_c,
+        avgtemp_c: number(row, "avgtemp", columns, options).unwrap_or((maxtemp_c + mintemp_c) / 2.0),
+        totalprecip_mm: number(row, "totalprecip", columns, options).unwrap_or(0.0),
+        chance_of_precip,
+        will_precip: number(row, "will_precip", columns, options).map_or(chance_of_precip >= 50.0, |v| v != 0.0),
+    })
+}
+
+fn marine_from_row(
+    row: &BTreeMap<String, Fact>,
+    options: &ImportOptions,
+    offsets: &HashMap<String, FixedOffset>,
+) -> Option<MarineRecord> {
+    let columns = MARINE_COLUMNS;
+    let location = options
+        .location
+        .clone()
+        .or_else(|| string(row, "location", columns, options))?;
+    let (time, local_time) = timestamps(row, columns, options, &location, offsets)?;
+
+    Some(MarineRecord {
+        provider: string(row, "provider", columns, options).unwrap_or(options.provider.clone()),
+        location,
+        time,
+        local_time,
+        sig_ht_mt: number(row, "sig_ht", columns, options),
+        swell_ht_mt: number(row, "swell_ht", columns, options),
+        swell_dir: number(row, "swell_dir", columns, options),
+        swell_period_secs: number(row, "swell_period_secs", columns, options),
+        water_temp_c: number(row, "water_temp", columns, options),
+    })
+}
+
+// Returns how many records were imported and how many were skipped, either because
+// they were already recorded or because they were missing required columns
+pub fn import(
+    working_directory: &Path,
+    options: &ImportOptions,
+    input: &str,
+) -> Result<(usize, usize), TransferError> {
+    let rows = parse_rows(input, options.format)?;
+    let total = rows.len();
+
+    let imported = match options.dataset {
+        Dataset::Observations => {
+            let recorded = history::load_observations(working_directory);
+            let offsets = offsets(recorded.iter().map(|o| (o.location.as_str(), o.time, o.local_time)));
+            let mut known: HashSet<(String, i64)> = recorded
+                .into_iter()
+                .map(|o| (o.location, o.time))
+                .collect();
+            let records: Vec<Observation> = rows
+                .iter()
+                .filter_map(|row| observation_from_row(row, options, &offsets))
+                .filter(|o| known.insert((o.location.clone(), o.time)))
+                .collect();
+            history::append(&working_directory.join(history::OBSERVATIONS_FILE), &records)
+                .map_err(|_| TransferError::FailedToRecord)?;
+            records.len()
+        }
+        Dataset::Forecasts => {
+            let mut known: HashSet<(String, i64, NaiveDate)> = history::load_forecasts(working_directory)
+                .into_iter()
+                .map(|f| (f.location, f.issued, f.date))
+                .collect();
+            let records: Vec<ForecastRecord> = rows
+                .iter()
+                .filter_map(|row| forecast_from_row(row, options))
+                .filter(|f| known.insert((f.location.clone(), f.issued, f.date)))
+                .collect();
+            history::record_forecasts(working_directory, &records)
+                .map_err(|_| TransferError::FailedToRecord)?;
+            records.len()
+        }
+        Dataset::Marine => {
+            let recorded = history::load_marine(working_directory);
+            let offsets = offsets(recorded.iter().map(|r| (r.location.as_str(), r.time, r.local_time)));
+            let mut known: HashSet<(String, i64)> = recorded
+                .into_iter()
+                .map(|r| (r.location, r.time))
+                .collect();
+            let records: Vec<MarineRecord> = rows
+                .iter()
+                .filter_map(|row| marine_from_row(row, options, &offsets))
+                .filter(|r| known.insert((r.location.clone(), r.time)))
+                .collect();
+            history::append(&working_directory.join(history::MARINE_FILE), &records)
+                .map_err(|_| TransferError::FailedToRecord)?;
+            records.len()
+        }
+    };
+
+    Ok((imported, total - imported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ImportOptions {
+        ImportOptions {
+            dataset: Dataset::Observations,
+            format: Format::Csv,
+            units: Units::Metric,
+            location: None,
+            provider: String::from("import"),
+            mappings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn quoted_fields_can_span_lines() {
+        let input = "location,temp,condition\r\nLake,11,\"Light\nrain, then \"\"sun\"\"\"\n\nLake,12,Clear";
+        let records = csv_records(input);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], (2, vec![String::from("Lake"), String::from("11"), String::from("Light\nrain, then \"sun\"")]));
+        assert_eq!(records[2].0, 5);
+    }
+
+    #[test]
+    fn missing_measurements_are_none() {
+        let rows = parse_rows("location,time,temp_c,humidity\nLake,1760003600,11,\n", Format::Csv).unwrap();
+        let observation = observation_from_row(&rows[0], &options(), &HashMap::new()).unwrap();
+        assert_eq!(observation.humidity, None);
+        assert_eq!(observation.pressure_mb, None);
+        assert_eq!(observation_values(&observation)[6].to_string(), "");
+    }
+
+    #[test]
+    fn epochs_take_the_recorded_offset() {
+        let recorded = NaiveDate::from_ymd_opt(2025, 10, 9).unwrap().and_hms_opt(10, 53, 20).unwrap();
+        let offsets = offsets(std::iter::once(("Lake", 1760000000, recorded)));
+        assert_eq!(offsets["Lake"], FixedOffset::east_opt(2 * 3600).unwrap());
+
+        let rows = parse_rows("location,time,temp_c\nLake,1760003600,11\n", Format::Csv).unwrap();
+        let observation = observation_from_row(&rows[0], &options(), &offsets).unwrap();
+        assert_eq!(observation.local_time, recorded + chrono::TimeDelta::hours(1));
+    }
+}
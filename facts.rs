@@ -0,0 +1,230 @@
+use crate::api::Api;
+use crate::astro;
+use crate::derived;
+use crate::models::{upcoming_hours, AirQuality, Tide};
+use json::JsonValue;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display};
+
+// Named values pulled out of responses and history, shared by notification rules
+// and output templates
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Fact {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+    values: BTreeMap<String, Fact>,
+    // Facts for each upcoming hour of the forecast, starting with the current hour
+    pub hours: Vec<Facts>,
+}
+
+impl Display for Fact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+impl Facts {
+    pub fn insert_number(&mut self, name: &str, value: f64) {
+        self.values.insert(name.to_string(), Fact::Number(value));
+    }
+
+    pub fn insert_text(&mut self, name: &str, text: &str) {
+        self.values
+            .insert(name.to_string(), Fact::Text(text.to_string()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Fact> {
+        self.values.get(name)
+    }
+
+    pub fn number(&self, name: &str) -> Option<f64> {
+        match self.values.get(name) {
+            Some(Fact::Number(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // Copies every value from `other` over these, leaving the hours alone
+    pub fn extend(&mut self, other: &Facts) {
+        self.values
+            .extend(other.values.iter().map(|(name, fact)| (name.clone(), fact.clone())));
+    }
+
+    fn add_entries(&mut self, object: &JsonValue) {
+        for (name, value) in object.entries() {
+            if let Some(number) = value.as_f64() {
+                self.insert_number(name, number);
+            } else if let Some(text) = value.as_str() {
+                self.insert_text(name, text);
+            }
+        }
+        if let Some(condition) = object["condition"]["text"].as_str() {
+            self.insert_text("condition", condition);
+        }
+        self.add_air_quality("", object);
+        derived::add_facts(self);
+    }
+
+    // aqi_pm2_5, aqi_us_epa_index, aqi_us, pollen_grass, ...
+    fn add_air_quality(&mut self, prefix: &str, object: &JsonValue) {
+        let air_quality = &object["air_quality"];
+        for (name, value) in air_quality.entries() {
+            if let Some(number) = value.as_f64() {
+                self.insert_number(&format!("{prefix}aqi_{}", name.replace('-', "_")), number);
+            }
+        }
+        let pm = |name: &str| air_quality[name].as_f64();
+        if let Some(aqi) = AirQuality::us_aqi(pm("pm2_5"), pm("pm10")) {
+            self.insert_number(&format!("{prefix}aqi_us"), aqi);
+        }
+        for (name, value) in object["pollen"].entries() {
+            if let Some(number) = value.as_f64() {
+                self.insert_number(&format!("{prefix}pollen_{}", name.to_lowercase()), number);
+            }
+        }
+    }
+
+    // Every scalar field of the current.json response, named as the provider names them
+    pub fn add_current(&mut self, response: &JsonValue) {
+        self.add_entries(&response["current"]);
+        if let Some(name) = response["location"]["name"].as_str() {
+            self.insert_text("location", name);
+        }
+    }
+
+    // Today's forecast, prefixed with forecast_ (forecast_maxtemp_c, ...)
+    pub fn add_forecast(&mut self, response: &JsonValue) {
+        let today = &response["forecast"]["forecastday"][0];
+        for (name, value) in today["day"].entries() {
+            if let Some(number) = value.as_f64() {
+                self.insert_number(&format!("forecast_{name}"), number);
+            }
+        }
+        if let Some(condition) = today["day"]["condition"]["text"].as_str() {
+            self.insert_text("forecast_condition", condition);
+        }
+        self.add_air_quality("forecast_", &today["day"]);
+
+        self.hours = upcoming_hours(response, 48)
+            .map(|hour| {
+                let mut facts = Facts::default();
+                facts.add_entries(hour);
+                facts
+            })
+            .collect();
+    }
+
+    // The current hour at sea (marine_sig_ht_mt, marine_water_temp_c, ...) and the
+    // next high and low tide (next_high_tide, minutes_until_low_tide, ...)
+    pub fn add_marine(&mut self, response: &JsonValue, now: chrono::DateTime<chrono::Utc>) {
+        if let Some(hour) = upcoming_hours(response, 1).next() {
+            for (name, value) in hour.entries() {
+                if let Some(number) = value.as_f64() {
+                    self.insert_number(&format!("marine_{name}"), number);
+                } else if let Some(text) = value.as_str() {
+                    self.insert_text(&format!("marine_{name}"), text);
+                }
+            }
+        }
+
+        let offset = astro::utc_offset(response).unwrap_or(*chrono::Local::now().offset());
+        let tides = Tide::list_from_json(response);
+        for kind in ["high", "low"] {
+            let next = tides.iter().find_map(|tide| {
+                if !tide.kind.as_deref()?.eq_ignore_ascii_case(kind) {
+                    return None;
+                }
+                let time = crate::history::parse_local_time(tide.time.as_deref()?)?
+                    .and_local_timezone(offset)
+                    .single()?;
+                (time > now).then_some((time, tide.height_mt))
+            });
+            if let Some((time, height)) = next {
+                self.insert_text(&format!("next_{kind}_tide"), &time.format("%H:%M").to_string());
+                self.insert_number(
+                    &format!("minutes_until_{kind}_tide"),
+                    (time.with_timezone(&chrono::Utc) - now).num_minutes() as f64,
+                );
+                if let Some(height) = height {
+                    self.insert_number(&format!("next_{kind}_tide_mt"), height);
+                }
+            }
+        }
+    }
+
+    pub fn add_alerts(&mut self, response: &JsonValue) {
+        let alerts = &response["alerts"]["alert"];
+        self.insert_number("alert_count", alerts.len() as f64);
+        if let Some(headline) = alerts[0]["headline"].as_str() {
+            self.insert_text("alert_headline", headline);
+        }
+        if let Some(severity) = alerts[0]["severity"].as_str() {
+            self.insert_text("alert_severity", severity);
+        }
+    }
+
+    // Today's sun and moon at the location (sunset, moon_phase, ...), and how long
+    // until each sun event comes round next (minutes_until_sunset, ...)
+    pub fn add_astronomy(&mut self, response: &JsonValue, now: chrono::DateTime<chrono::Utc>) {
+        let location = &response["location"];
+        let (Some(lat), Some(lon)) = (location["lat"].as_f64(), location["lon"].as_f64()) else {
+            return;
+        };
+        let offset = astro::utc_offset(response).unwrap_or(*chrono::Local::now().offset());
+        let today = astro::Astronomy::calculate(now.with_timezone(&offset).date_naive(), lat, lon, offset);
+        let times = [
+            ("sunrise", &today.sunrise),
+            ("sunset", &today.sunset),
+            ("civil_dawn", &today.civil_dawn),
+            ("civil_dusk", &today.civil_dusk),
+            ("nautical_dawn", &today.nautical_dawn),
+            ("nautical_dusk", &today.nautical_dusk),
+            ("golden_hour_morning_end", &today.golden_hour_morning_end),
+            ("golden_hour_evening_start", &today.golden_hour_evening_start),
+        ];
+        for (name, time) in times {
+            if let Some(time) = time {
+                self.insert_text(name, time);
+            }
+        }
+        if let Some(phase) = &today.moon_phase {
+            self.insert_text("moon_phase", phase);
+        }
+        if let Some(illumination) = today.moon_illumination {
+            self.insert_number("moon_illumination", illumination);
+        }
+        for (name, elevation, rising) in astro::EVENTS {
+            if let Some(minutes) = astro::minutes_until(now, lat, lon, offset, elevation, rising) {
+                self.insert_number(&format!("minutes_until_{name}"), minutes.round());
+            }
+        }
+    }
+
+    // Everything available from the responses the api has cached
+    pub fn from_api(api: &Api) -> Self {
+        let mut facts = Self::default();
+        if let Some((response, timestamp)) = api.get_cached_current() {
+            facts.add_current(response);
+            facts.add_astronomy(response, chrono::Utc::now());
+            facts.insert_number("age_minutes", (timestamp.elapsed().as_secs() / 60) as f64);
+        }
+        if let Some((response, _timestamp)) = api.get_cached_forecast() {
+            facts.add_forecast(response);
+        }
+        if let Some((response, _timestamp)) = api.get_cached_alerts() {
+            facts.add_alerts(response);
+        }
+        if let Some((response, _timestamp)) = api.get_cached_marine() {
+            facts.add_marine(response, chrono::Utc::now());
+        }
+        facts
+    }
+}
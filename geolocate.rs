@@ -0,0 +1,191 @@
+use crate::api::Location;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+const GPSD_ADDRESS: &str = "127.0.0.1:2947";
+const GPSD_TIMEOUT: Duration = Duration::from_secs(2);
+#[cfg(target_os = "linux")]
+const GEOCLUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How Location::Auto gets resolved before each request, e.g.
+// auto_location: (geoclue: false, home: Some(City("Houghton")))
+// Sources are tried in order: gpsd, geoclue, home, then WeatherAPI's own IP lookup.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AutoLocation {
+    #[serde(default = "enabled")]
+    pub gpsd: bool,
+    #[serde(default = "enabled")]
+    pub geoclue: bool,
+    #[serde(default)]
+    pub home: Option<Location>,
+    // How far the daemon has to be carried before it fetches again early
+    #[serde(default = "default_move_km")]
+    pub move_km: f64,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_move_km() -> f64 {
+    5.0
+}
+
+impl Default for AutoLocation {
+    fn default() -> Self {
+        Self {
+            gpsd: enabled(),
+            geoclue: enabled(),
+            home: None,
+            move_km: default_move_km(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Gpsd,
+    Geoclue,
+    Home,
+    Ip,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Gpsd => write!(f, "gpsd"),
+            Self::Geoclue => write!(f, "geoclue"),
+            Self::Home => write!(f, "home location"),
+            Self::Ip => write!(f, "IP address"),
+        }
+    }
+}
+
+// Asks a local gpsd for its latest fix
+fn gpsd() -> Option<(f64, f64)> {
+    let address = GPSD_ADDRESS.parse().ok()?;
+    let mut stream = TcpStream::connect_timeout(&address, GPSD_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(GPSD_TIMEOUT)).ok()?;
+    stream
+        .write_all(b"?WATCH={\"enable\":true,\"json\":true};?POLL;\n")
+        .ok()?;
+
+    let deadline = Instant::now() + GPSD_TIMEOUT;
+    for line in BufReader::new(stream).lines() {
+        if Instant::now() > deadline {
+            break;
+        }
+        let Ok(report) = json::parse(&line.ok()?) else {
+            continue;
+        };
+        // POLL answers with the last fix, TPV reports keep coming while watching
+        let tpv = match report["class"].as_str() {
+            Some("POLL") => &report["tpv"][0],
+            Some("TPV") => &report,
+            _ => continue,
+        };
+        // Mode 2 is a 2D fix and 3 a 3D one, anything less has no position
+        if tpv["mode"].as_u8().unwrap_or(0) >= 2 {
+            return Some((tpv["lat"].as_f64()?, tpv["lon"].as_f64()?));
+        }
+    }
+    None
+}
+
+// GeoClue combines Wi-Fi, cell and GPS positioning over the system bus. A client has
+// to be started and then waited on until it has found the location.
+#[cfg(target_os = "linux")]
+fn geoclue() -> Option<(f64, f64)> {
+    use zbus::blocking::{proxy::Builder, Connection, Proxy};
+    use zbus::proxy::CacheProperties;
+    use zbus::zvariant::OwnedObjectPath;
+
+    let connection = Connection::system().ok()?;
+    let proxy = |path: String, interface: &'static str| -> Option<Proxy> {
+        Builder::new(&connection)
+            .destination("org.freedesktop.GeoClue2")
+            .ok()?
+            .path(path)
+            .ok()?
+            .interface(interface)
+            .ok()?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .ok()
+    };
+
+    let manager = proxy(
+        String::from("/org/freedesktop/GeoClue2/Manager"),
+        "org.freedesktop.GeoClue2.Manager",
+    )?;
+    let client_path: OwnedObjectPath = manager.call("GetClient", &()).ok()?;
+    let client = proxy(client_path.to_string(), "org.freedesktop.GeoClue2.Client")?;
+    client.set_property("DesktopId", "weathd").ok()?;
+    // Street level, enough for weather and doesn't need to wait for GPS
+    client.set_property("RequestedAccuracyLevel", 6u32).ok()?;
+    client.call::<_, _, ()>("Start", &()).ok()?;
+
+    let deadline = Instant::now() + GEOCLUE_TIMEOUT;
+    let mut position = None;
+    while position.is_none() && Instant::now() < deadline {
+        position = client
+            .get_property::<OwnedObjectPath>("Location")
+            .ok()
+            .filter(|path| path.as_str() != "/")
+            .and_then(|path| proxy(path.to_string(), "org.freedesktop.GeoClue2.Location"))
+            .and_then(|location| {
+                Some((
+                    location.get_property::<f64>("Latitude").ok()?,
+                    location.get_property::<f64>("Longitude").ok()?,
+                ))
+            });
+        if position.is_none() {
+            std::thread::sleep(Duration::from_millis(250));
+        }
+    }
+    let _ = client.call::<_, _, ()>("Stop", &());
+    position
+}
+
+#[cfg(not(target_os = "linux"))]
+fn geoclue() -> Option<(f64, f64)> {
+    None
+}
+
+pub fn resolve(settings: &AutoLocation) -> (Location, Source) {
+    let sources = [
+        (settings.gpsd, gpsd as fn() -> Option<(f64, f64)>, Source::Gpsd),
+        (settings.geoclue, geoclue, Source::Geoclue),
+    ];
+    for (enabled, locate, source) in sources {
+        if let Some((lat, lon)) = enabled.then(locate).flatten() {
+            return (Location::Coordinate(lat, lon), source);
+        }
+    }
+    match &settings.home {
+        Some(home) => (home.clone(), Source::Home),
+        None => (Location::Auto, Source::Ip),
+    }
+}
+
+// The location to send for `q`, only Auto needs looking up
+pub fn locate(q: &Location, settings: &AutoLocation) -> Location {
+    match q {
+        Location::Auto => resolve(settings).0,
+        q => q.clone(),
+    }
+}
+
+// Great circle distance
+pub fn distance_km((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let half_lat = (lat2 - lat1) / 2.0;
+    let half_lon = (lon2 - lon1).to_radians() / 2.0;
+    let a = half_lat.sin().powi(2) + lat1.cos() * lat2.cos() * half_lon.sin().powi(2);
+    6371.0 * 2.0 * a.sqrt().asin()
+}
@@ -0,0 +1,246 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use json::JsonValue;
+use ron::extensions::Extensions;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+pub const OBSERVATIONS_FILE: &str = "observations.ron";
+pub const FORECASTS_FILE: &str = "forecasts.ron";
+pub const MARINE_FILE: &str = "marine.ron";
+
+// A single reading of current conditions, as reported by the provider
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Observation {
+    pub provider: String,
+    pub location: String,
+    pub time: i64,
+    pub local_time: NaiveDateTime,
+    pub temp_c: f64,
+    pub feelslike_c: f64,
+    #[serde(default, deserialize_with = "measurement")]
+    pub humidity: Option<f64>,
+    #[serde(default, deserialize_with = "measurement")]
+    pub pressure_mb: Option<f64>,
+    pub precip_mm: f64,
+    #[serde(default, deserialize_with = "measurement")]
+    pub wind_kph: Option<f64>,
+    pub condition: String,
+}
+
+// One day of a forecast, stamped with when it was issued and how far ahead it looks
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ForecastRecord {
+    pub provider: String,
+    pub location: String,
+    pub issued: i64,
+    pub date: NaiveDate,
+    pub lead_days: i64,
+    pub maxtemp_c: f64,
+    pub mintemp_c: f64,
+    pub avgtemp_c: f64,
+    pub totalprecip_mm: f64,
+    pub chance_of_precip: f64,
+    pub will_precip: bool,
+}
+
+// Sea conditions for the hour the marine forecast was fetched in
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MarineRecord {
+    pub provider: String,
+    pub location: String,
+    pub time: i64,
+    pub local_time: NaiveDateTime,
+    #[serde(default, deserialize_with = "measurement")]
+    pub sig_ht_mt: Option<f64>,
+    #[serde(default, deserialize_with = "measurement")]
+    pub swell_ht_mt: Option<f64>,
+    #[serde(default, deserialize_with = "measurement")]
+    pub swell_dir: Option<f64>,
+    #[serde(default, deserialize_with = "measurement")]
+    pub swell_period_secs: Option<f64>,
+    #[serde(default, deserialize_with = "measurement")]
+    pub water_temp_c: Option<f64>,
+}
+
+#[derive(Debug)]
+pub enum HistoryError {
+    FailedToOpenFile,
+    FailedToWrite,
+}
+
+impl Observation {
+    pub fn from_current(provider: &str, location: &str, response: &JsonValue) -> Option<Self> {
+        let current = &response["current"];
+        Some(Self {
+            provider: provider.to_string(),
+            location: location.to_string(),
+            time: current["last_updated_epoch"].as_i64()?,
+            local_time: parse_local_time(current["last_updated"].as_str()?)?,
+            temp_c: current["temp_c"].as_f64()?,
+            feelslike_c: current["feelslike_c"].as_f64()?,
+            humidity: current["humidity"].as_f64(),
+            pressure_mb: current["pressure_mb"].as_f64(),
+            precip_mm: current["precip_mm"].as_f64().unwrap_or(0.0),
+            wind_kph: current["wind_kph"].as_f64(),
+            condition: current["condition"]["text"].to_string(),
+        })
+    }
+}
+
+impl ForecastRecord {
+    pub fn from_forecast(
+        provider: &str,
+        location: &str,
+        issued: i64,
+        response: &JsonValue,
+    ) -> Vec<Self> {
+        let Some(issued_date) = response["location"]["localtime"]
+            .as_str()
+            .and_then(parse_local_time)
+            .map(|t| t.date())
+        else {
+            return Vec::new();
+        };
+
+        response["forecast"]["forecastday"]
+            .members()
+            .filter_map(|forecastday| {
+                let date = NaiveDate::parse_from_str(forecastday["date"].as_str()?, "%Y-%m-%d").ok()?;
+                let day = &forecastday["day"];
+                let chance_of_rain = day["daily_chance_of_rain"].as_f64().unwrap_or(0.0);
+                let chance_of_snow = day["daily_chance_of_snow"].as_f64().unwrap_or(0.0);
+                Some(Self {
+                    provider: provider.to_string(),
+                    location: location.to_string(),
+                    issued,
+                    date,
+                    lead_days: (date - issued_date).num_days(),
+                    maxtemp_c: day["maxtemp_c"].as_f64()?,
+                    mintemp_c: day["mintemp_c"].as_f64()?,
+                    avgtemp_c: day["avgtemp_c"].as_f64()?,
+                    totalprecip_mm: day["totalprecip_mm"].as_f64().unwrap_or(0.0),
+                    chance_of_precip: chance_of_rain.max(chance_of_snow),
+                    will_precip: day["daily_will_it_rain"].as_u8() == Some(1)
+                        || day["daily_will_it_snow"].as_u8() == Some(1),
+                })
+            })
+            .collect()
+    }
+}
+
+impl MarineRecord {
+    pub fn from_marine(provider: &str, location: &str, response: &JsonValue) -> Option<Self> {
+        let hour = crate::models::upcoming_hours(response, 1).next()?;
+        Some(Self {
+            provider: provider.to_string(),
+            location: location.to_string(),
+            time: hour["time_epoch"].as_i64()?,
+            local_time: parse_local_time(hour["time"].as_str()?)?,
+            sig_ht_mt: hour["sig_ht_mt"].as_f64(),
+            swell_ht_mt: hour["swell_ht_mt"].as_f64(),
+            swell_dir: hour["swell_dir"].as_f64(),
+            swell_period_secs: hour["swell_period_secs"].as_f64(),
+            water_temp_c: hour["water_temp_c"].as_f64(),
+        })
+    }
+}
+
+// Older records stored measurements they didn't have as NaN
+fn measurement<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Ok(Option::<f64>::deserialize(deserializer)?.filter(|value| !value.is_nan()))
+}
+
+// Measurements are written bare and missing ones as None, so records from before
+// they were optional still load
+fn options() -> ron::Options {
+    ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME)
+}
+
+pub fn parse_local_time(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").ok()
+}
+
+// Records are stored one RON value per line so new entries can simply be appended
+pub fn append<T: Serialize>(path: &Path, records: &[T]) -> Result<(), HistoryError> {
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+        return Err(HistoryError::FailedToOpenFile);
+    };
+    for record in records {
+        let Ok(line) = options().to_string(record) else {
+            return Err(HistoryError::FailedToWrite);
+        };
+        if writeln!(&mut file, "{line}").is_err() {
+            return Err(HistoryError::FailedToWrite);
+        }
+    }
+    Ok(())
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| match options().from_str(&line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                eprintln!(
+                    "Skipping malformed record on line {} of {}: {:?}",
+                    i + 1,
+                    path.to_str().unwrap(),
+                    e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+// Imported records can be appended out of order, so observations are sorted on load
+pub fn load_observations(working_directory: &Path) -> Vec<Observation> {
+    let mut observations: Vec<Observation> = load(&working_directory.join(OBSERVATIONS_FILE));
+    observations.sort_by_key(|o| o.time);
+    observations
+}
+
+pub fn load_forecasts(working_directory: &Path) -> Vec<ForecastRecord> {
+    load(&working_directory.join(FORECASTS_FILE))
+}
+
+pub fn load_marine(working_directory: &Path) -> Vec<MarineRecord> {
+    let mut records: Vec<MarineRecord> = load(&working_directory.join(MARINE_FILE));
+    records.sort_by_key(|r| r.time);
+    records
+}
+
+pub fn record_observation(
+    working_directory: &Path,
+    observation: &Observation,
+) -> Result<(), HistoryError> {
+    append(
+        &working_directory.join(OBSERVATIONS_FILE),
+        std::slice::from_ref(observation),
+    )
+}
+
+pub fn record_forecasts(
+    working_directory: &Path,
+    forecasts: &[ForecastRecord],
+) -> Result<(), HistoryError> {
+    append(&working_directory.join(FORECASTS_FILE), forecasts)
+}
+
+pub fn record_marine(working_directory: &Path, record: &MarineRecord) -> Result<(), HistoryError> {
+    append(
+        &working_directory.join(MARINE_FILE),
+        std::slice::from_ref(record),
+    )
+}
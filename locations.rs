@@ -0,0 +1,64 @@
+use crate::api::{ApiRequestConfiguration, Location, RequestTypes};
+use crate::digest::Channel;
+use crate::quiet::QuietConfiguration;
+use crate::rules::Rule;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const LOCATIONS_FILE: &str = "locations.ron";
+
+// A place the daemon watches alongside the others, e.g.
+// NamedLocation(name: "office", q: City("Houghton"), rules: [...])
+// Anything left out falls back to api_config.ron and daemon_config.ron.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NamedLocation {
+    pub name: String,
+    pub q: Location,
+    #[serde(default)]
+    pub requests: Option<RequestTypes>,
+    // Used on top of the rules in daemon_config.ron
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    // Keep recording and caching, but never send desktop notifications
+    #[serde(default)]
+    pub silent: bool,
+    // Where its digests go instead of the channels in daemon_config.ron
+    #[serde(default)]
+    pub channels: Option<Vec<Channel>>,
+    // Its own quiet hours instead of the ones in daemon_config.ron, snoozes still apply
+    #[serde(default)]
+    pub quiet: Option<QuietConfiguration>,
+}
+
+impl NamedLocation {
+    // Each location caches its responses in its own directory so bars and the
+    // dashboard can read them separately
+    pub fn directory(&self, working_directory: &Path) -> PathBuf {
+        working_directory.join("locations").join(&self.name)
+    }
+
+    pub fn api_config(&self, base: &ApiRequestConfiguration) -> ApiRequestConfiguration {
+        ApiRequestConfiguration {
+            q: self.q.clone(),
+            requests: self.requests.clone().unwrap_or(base.requests.clone()),
+            ..base.clone()
+        }
+    }
+}
+
+pub fn load(working_directory: &Path) -> Vec<NamedLocation> {
+    let Ok(data) = std::fs::read_to_string(working_directory.join(LOCATIONS_FILE)) else {
+        return Vec::new();
+    };
+    match ron::from_str(&data) {
+        Ok(locations) => locations,
+        Err(e) => {
+            eprintln!("Failed to parse {LOCATIONS_FILE}: {e:?}");
+            Vec::new()
+        }
+    }
+}
+
+pub fn find<'a>(locations: &'a [NamedLocation], name: &str) -> Option<&'a NamedLocation> {
+    locations.iter().find(|location| location.name.eq_ignore_ascii_case(name))
+}
@@ -1,22 +1,48 @@
 use std::{
-    fs::{read, File},
+    collections::BTreeMap,
+    fs::File,
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::Duration,
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
+mod advisories;
 mod api;
+mod astro;
+mod bar;
+mod chart;
+mod config;
 mod daemon;
+mod dashboard;
+mod derived;
+mod digest;
+mod export;
+mod facts;
+mod geolocate;
+mod history;
+mod locations;
+mod models;
+mod notify;
+mod output;
+mod quiet;
+mod rules;
+mod stats;
+mod template;
+mod units;
 mod utils;
+mod verify;
 
 use api::*;
 use daemon::*;
-use utils::DurationWrapper;
+use utils::{DurationWrapper, Schedule};
 
 #[derive(Parser)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short, long, default_value_t = false)]
     daemonize: bool,
 
@@ -32,183 +58,678 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     save_to_config: bool,
 
-    #[arg(long, default_value_t = true)]
-    current_weather: bool,
+    /// Get the current weather, --current-weather=false to leave it out
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    current_weather: Option<bool>,
 
-    #[arg(long, default_value_t = true)]
-    forecast: bool,
+    /// Get the forecast, --forecast=false to leave it out
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    forecast: Option<bool>,
 
-    #[arg(long, default_value_t = false)]
-    alerts: bool,
+    /// Get weather alerts
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    alerts: Option<bool>,
+
+    /// Get waves, swell, water temperature and tides for a coastal location
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    marine: Option<bool>,
+
+    /// Days of forecast to get
+    #[arg(long, value_name = "N")]
+    days: Option<usize>,
 
     #[arg(long, default_value = None)]
     city: Option<String>,
 
-    #[arg(long, default_value_t = Duration::new(600,0).into())]
-    daemon_update_interval: DurationWrapper,
+    /// A location from locations.ron by name, or any other location such as a city,
+    /// 48.8,2.3, 90210, SW1A 1AA, metar:EGLL, iata:DXB, id:2801268, an IP address or auto:ip
+    #[arg(long, global = true)]
+    location: Option<String>,
 
-    #[arg(long, default_value_t = Duration::new(21600,0).into())]
-    daemon_notif_interval: DurationWrapper,
-}
+    /// Compare the current weather at every location in locations.ron
+    #[arg(long, default_value_t = false, conflicts_with_all = ["city", "location"])]
+    all: bool,
 
-fn main() {
-    let args = Args::parse();
-    let working_directory = args
-        .working_directory
-        .as_ref()
-        .map(|s| s.into())
-        .unwrap_or(default_working_directory());
+    /// How to print results. Everything but text follows a versioned schema
+    #[arg(long, value_enum, default_value_t = output::OutputFormat::Text)]
+    format: output::OutputFormat,
 
-    let api_key = args.api_key.clone().or_else(|| load_api_key(&working_directory)).expect(
-        "TODO: implement config file, failed to grab api_key. An api key can be specified with the -a option",
-    );
-    let mut api = Api::new(api_key.clone());
+    /// Show the forecast for each of the next N hours (24 if N is left out)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "24")]
+    hourly: Option<usize>,
+
+    /// Get the weather for a single date instead of the current conditions and forecast
+    #[arg(long, value_name = "YYYY-MM-DD", conflicts_with_all = ["from", "to"])]
+    date: Option<chrono::NaiveDate>,
+
+    /// Start of a range of dates, past dates give the recorded weather
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    from: Option<chrono::NaiveDate>,
+
+    /// End of the range started with --from, defaults to the same day
+    #[arg(long, value_name = "YYYY-MM-DD", requires = "from")]
+    to: Option<chrono::NaiveDate>,
+
+    /// Draw charts of the hourly and daily forecast
+    #[arg(long, default_value_t = false)]
+    charts: bool,
 
-    let mut api_config = ApiRequestConfiguration::default();
-    let mut daemon_config = DaemonConfiguration::default();
+    /// Only use ASCII characters when drawing charts
+    #[arg(long, default_value_t = false)]
+    ascii: bool,
 
-    load_from_config(&working_directory, &mut daemon_config, &mut api_config);
+    /// Recommend what to wear, whether to take an umbrella and when to go out, from the
+    /// advisories in advisories.ron or the built in ones
+    #[arg(long, default_value_t = false)]
+    advice: bool,
 
-    daemon_config.exec_interval = args.daemon_update_interval.clone().into();
-    daemon_config.notif_interval = args.daemon_notif_interval.clone().into();
+    /// Units to show values in, instead of the ones in units.ron
+    #[arg(long, value_enum)]
+    units: Option<units::UnitSystem>,
 
-    api_config.requests = api::RequestTypes {
+    /// Show one quantity in other units, e.g. --unit speed=knots or --unit pressure=mmhg
+    #[arg(long = "unit", value_name = "QUANTITY=UNIT", value_parser = units::parse_choice)]
+    unit_choices: Vec<units::UnitChoice>,
+
+    /// When the daemon polls, an interval like 10m, times like 07:00,17:30, a cron
+    /// expression like "*/10 9-17 * * Mon-Fri", or several separated by ';'
+    #[arg(long)]
+    daemon_update_interval: Option<Schedule>,
+
+    /// When the daemon sends the current weather, in the same forms as --daemon-update-interval
+    #[arg(long)]
+    daemon_notif_interval: Option<Schedule>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare forecasts recorded by the daemon against the conditions that were observed
+    Verify,
+    /// Summarize the observation history recorded by the daemon
+    Stats {
+        #[arg(long, value_enum, default_value_t = stats::Period::Daily)]
+        period: stats::Period,
+
+        /// Base temperature in °C for heating and cooling degree days
+        #[arg(long)]
+        base: Option<f64>,
+
+        /// Only show statistics for this location
+        #[arg(long)]
+        location: Option<String>,
+    },
+    /// Write recorded observations or forecasts out for use in other tools
+    Export {
+        #[arg(value_enum)]
+        dataset: export::Dataset,
+
+        #[arg(long, value_enum, default_value_t = export::Format::Csv)]
+        format: export::Format,
+
+        /// Comma separated list of columns to include, e.g. time,temp,humidity
+        #[arg(long, value_delimiter = ',')]
+        columns: Option<Vec<String>>,
+
+        /// First date to include (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+
+        /// Last date to include (YYYY-MM-DD)
+        #[arg(long)]
+        to: Option<chrono::NaiveDate>,
+
+        #[arg(long)]
+        location: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = export::Units::Metric)]
+        units: export::Units,
+
+        /// File to write to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add observations or forecasts from a file to the recorded history
+    Import {
+        #[arg(value_enum)]
+        dataset: export::Dataset,
+
+        file: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = export::Format::Csv)]
+        format: export::Format,
+
+        /// Units of columns whose names don't say which units they are in
+        #[arg(long, value_enum, default_value_t = export::Units::Metric)]
+        units: export::Units,
+
+        /// Location to file the records under, required if the file has no location column
+        #[arg(long)]
+        location: Option<String>,
+
+        #[arg(long, default_value = "import")]
+        provider: String,
+
+        /// Read a column of the file as one of ours, e.g. --map OutTemp=temp_f
+        #[arg(long = "map", value_parser = parse_mapping)]
+        mappings: Vec<(String, String)>,
+    },
+    /// Print the daemon's latest weather in the format a status bar expects
+    Bar {
+        #[arg(value_enum)]
+        mode: bar::BarMode,
+
+        /// Main text, e.g. "{temp:.0}{temp_unit} {condition}"
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Waybar tooltip
+        #[arg(long)]
+        tooltip: Option<String>,
+
+        /// Shortened text for i3blocks and tmux
+        #[arg(long)]
+        short: Option<String>,
+
+        /// Cached weather older than this is shown as stale
+        #[arg(long, default_value_t = Duration::new(1800, 0).into())]
+        max_age: DurationWrapper,
+    },
+    /// Open a full screen view of the daemon's latest weather
+    Dashboard,
+    /// Look up places by name, postcode or coordinates and optionally save one as the location
+    Search {
+        text: String,
+
+        /// Save a result to api_config.ron, asking which one if there are several
+        #[arg(long, default_value_t = false)]
+        save: bool,
+
+        /// Result number to save without asking
+        #[arg(long, value_name = "N", requires = "save")]
+        pick: Option<usize>,
+    },
+    /// Hold back the daemon's notifications for a while, 0s ends a snooze early
+    Snooze {
+        duration: DurationWrapper,
+    },
+    /// Sunrise, sunset, twilight, golden hour and the moon for a day
+    Astronomy {
+        /// Day to show, today if left out
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        date: Option<chrono::NaiveDate>,
+
+        /// Calculate locally instead of asking the provider, needs a coordinate location
+        #[arg(long, default_value_t = false)]
+        offline: bool,
+    },
+    /// Inspect the settings from the built in defaults, config files, WEATHD_* environment
+    /// variables and flags
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the settings in effect
+    Show {
+        /// Also print where each setting came from
+        #[arg(long, default_value_t = false)]
+        origin: bool,
+    },
+}
+
+fn parse_mapping(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!("expected COLUMN=NAME, found {s}")),
+    }
+}
+
+// The settings given on the command line, which override the config files and environment
+fn flags(args: &Args, named_locations: &[locations::NamedLocation]) -> config::Flags {
+    let mut q = args.city.clone().map(|city| (Location::City(city), "--city"));
+    if let Some(name) = &args.location {
+        match locations::find(named_locations, name) {
+            Some(location) => q = Some((location.q.clone(), "--location")),
+            None => match name.parse() {
+                Ok(location) => q = Some((location, "--location")),
+                Err(e) => {
+                    eprintln!("Invalid location {name}: {e}");
+                    std::process::exit(2);
+                }
+            },
+        }
+    }
+    config::Flags {
+        q,
+        days: args.days,
         current: args.current_weather,
-        alerts: args.alerts,
         forecast: args.forecast,
-    };
-
-    if let Some(city) = args.city {
-        api_config.q = Location::City(city);
+        alerts: args.alerts,
+        marine: args.marine,
+        exec_interval: args.daemon_update_interval.clone(),
+        notif_interval: args.daemon_notif_interval.clone(),
+        working_directory: args.working_directory.as_ref().map(PathBuf::from),
     }
+}
 
-    if let Some(path) = args.working_directory {
-        daemon_config.working_directory = path.into();
+fn main() {
+    let args = Args::parse();
+    let working_directory = config::working_directory(args.working_directory.as_ref());
+
+    let mut display_units = match args.units {
+        Some(system) => units::DisplayUnits {
+            system,
+            ..Default::default()
+        },
+        None => units::load(&working_directory),
+    };
+    for choice in &args.unit_choices {
+        display_units.choose(*choice);
     }
 
-    if let Some(days) = Some(3) {
-        api_config.days = Some(days);
+    match &args.command {
+        Some(Command::Verify) => {
+            let skill = verify::verify(
+                &history::load_forecasts(&working_directory),
+                &history::load_observations(&working_directory),
+            );
+            verify::print_report(&skill);
+            return;
+        }
+        Some(Command::Stats {
+            period,
+            base,
+            location,
+        }) => {
+            let mut stats_config = config::resolve(&working_directory, &config::Flags::default()).daemon.stats;
+            if let Some(base) = base {
+                stats_config.degree_day_base_c = *base;
+            }
+
+            let mut by_location: BTreeMap<String, Vec<history::Observation>> = BTreeMap::new();
+            for observation in history::load_observations(&working_directory) {
+                by_location
+                    .entry(observation.location.clone())
+                    .or_default()
+                    .push(observation);
+            }
+            if by_location.is_empty() {
+                println!("No observations recorded yet. Start the daemon with -d to begin recording.");
+            }
+            for (name, observations) in &by_location {
+                if location.as_ref().is_some_and(|location| location != name) {
+                    continue;
+                }
+                stats::print_report(name, observations, *period, &stats_config);
+            }
+            return;
+        }
+        Some(Command::Export {
+            dataset,
+            format,
+            columns,
+            from,
+            to,
+            location,
+            units,
+            output,
+        }) => {
+            let options = export::ExportOptions {
+                dataset: *dataset,
+                format: *format,
+                columns: columns.clone(),
+                from: *from,
+                to: *to,
+                location: location.clone(),
+                units: *units,
+            };
+            let result = match output {
+                Some(path) => match File::create(path) {
+                    Ok(mut file) => export::export(&working_directory, &options, &mut file),
+                    Err(e) => {
+                        eprintln!("Failed to create {}: {e:?}", path.to_str().unwrap());
+                        std::process::exit(1);
+                    }
+                },
+                None => export::export(&working_directory, &options, &mut std::io::stdout().lock()),
+            };
+            match result {
+                Ok(count) => eprintln!("Exported {count} record(s)"),
+                Err(e) => {
+                    eprintln!("Export failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Import {
+            dataset,
+            file,
+            format,
+            units,
+            location,
+            provider,
+            mappings,
+        }) => {
+            let Ok(input) = std::fs::read_to_string(file) else {
+                eprintln!("Failed to read {}", file.to_str().unwrap());
+                std::process::exit(1);
+            };
+            let options = export::ImportOptions {
+                dataset: *dataset,
+                format: *format,
+                units: *units,
+                location: location.clone(),
+                provider: provider.clone(),
+                mappings: mappings.clone(),
+            };
+            match export::import(&working_directory, &options, &input) {
+                Ok((imported, skipped)) => {
+                    println!("Imported {imported} record(s), skipped {skipped} duplicate or incomplete record(s)")
+                }
+                Err(e) => {
+                    eprintln!("Import failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Bar {
+            mode,
+            template,
+            tooltip,
+            short,
+            max_age,
+        }) => {
+            let mut api = Api::new(String::new());
+            api.load_cache(&location_directory(&working_directory, args.location.as_deref()));
+            let options = bar::BarOptions {
+                mode: *mode,
+                template: template.clone(),
+                tooltip: tooltip.clone(),
+                short: short.clone(),
+                max_age: max_age.clone().into(),
+            };
+            let mut facts = facts::Facts::from_api(&api);
+            display_units.add_facts(&mut facts);
+            bar::print(&facts, &options);
+            return;
+        }
+        Some(Command::Snooze { duration }) => {
+            match quiet::snooze(&working_directory, duration.clone().into()) {
+                Ok(Some(until)) => println!("Notifications snoozed until {}", until.format("%Y-%m-%d %H:%M")),
+                Ok(None) => println!("Notifications are no longer snoozed"),
+                Err(e) => {
+                    eprintln!("Failed to snooze: {e}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Dashboard) => {
+            let api_config = config::resolve(&working_directory, &config::Flags::default()).api;
+            let named = locations::load(&working_directory);
+            let locations = if named.is_empty() {
+                vec![dashboard::DashboardLocation {
+                    name: format!("{}", api_config.q),
+                    directory: working_directory.clone(),
+                }]
+            } else {
+                named
+                    .iter()
+                    .map(|location| dashboard::DashboardLocation {
+                        name: location.name.clone(),
+                        directory: location.directory(&working_directory),
+                    })
+                    .collect()
+            };
+            if let Err(e) = dashboard::run(&working_directory, locations, display_units) {
+                eprintln!("Dashboard failed: {e:?}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Search { text, save, pick }) => {
+            let api_key = args.api_key.clone().or_else(|| load_api_key(&working_directory)).expect(
+                "TODO: implement config file, failed to grab api_key. An api key can be specified with the -a option",
+            );
+            let places = match Api::new(api_key).search(text) {
+                Ok(response) if response["error"].is_null() => models::Place::list_from_json(&response),
+                Ok(response) => {
+                    eprintln!("Search Api Call failed with: {}", response["error"]["message"]);
+                    std::process::exit(4);
+                }
+                Err(e) => {
+                    eprintln!("Search Api Call failed with: {}", e.message());
+                    std::process::exit(e.exit_code());
+                }
+            };
+            output::print_places(&places, args.format);
+            if !save {
+                return;
+            }
+
+            let Some(place) = pick_place(&places, *pick) else {
+                return;
+            };
+            let Some(id) = place.id else {
+                eprintln!("The provider gave no id for that place");
+                std::process::exit(1);
+            };
+            let mut configuration = config::resolve(&working_directory, &config::Flags::default());
+            configuration.api.q = Location::SearchID(id);
+            if let Err(e) = config::save(&working_directory, &configuration, &["q"]) {
+                eprintln!("Failed to save the location: {e}");
+                std::process::exit(1);
+            }
+            println!(
+                "Saved {} as the location",
+                place.name.as_deref().unwrap_or("the place")
+            );
+            return;
+        }
+        Some(Command::Astronomy { date, offline }) => {
+            let api_config = config::resolve(&working_directory, &config::Flags::default()).api;
+            let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let q = geolocate::locate(&api_config.q, &api_config.auto_location);
+
+            if !offline {
+                let api_key = args.api_key.clone().or_else(|| load_api_key(&working_directory));
+                let response = api_key.map(|key| Api::new(key).astronomy(&q, date));
+                match response {
+                    Some(Ok(response)) if response["error"].is_null() => {
+                        if let Some(astronomy) = astro::Astronomy::from_json(&response) {
+                            let location = models::LocationInfo::from_json(&response);
+                            output::print_astronomy(&astronomy, location, args.format);
+                            return;
+                        }
+                    }
+                    Some(Ok(response)) => eprintln!(
+                        "Get Astronomy Api Call failed with: {}",
+                        response["error"]["message"]
+                    ),
+                    Some(Err(e)) => eprintln!("Get Astronomy Api Call failed with: {}", e.message()),
+                    None => {}
+                }
+            }
+
+            // Without the provider only a coordinate says where the location is
+            let Location::Coordinate(lat, lon) = q else {
+                eprintln!("Calculating astronomy offline needs a coordinate location");
+                std::process::exit(1);
+            };
+            // Times are shown in the place's own offset, taken from a response the daemon
+            // cached for somewhere within 50 km, and otherwise worked out from the longitude
+            let mut directories = vec![working_directory.clone()];
+            for location in locations::load(&working_directory) {
+                directories.push(location.directory(&working_directory));
+            }
+            let cached = directories.iter().find_map(|directory| {
+                let mut api = Api::new(String::new());
+                api.load_cache(directory);
+                let cached = [api.get_cached_current(), api.get_cached_forecast()]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|(response, _timestamp)| {
+                        let info = models::LocationInfo::from_json(response);
+                        if geolocate::distance_km((info.lat?, info.lon?), (lat, lon)) > 50.0 {
+                            return None;
+                        }
+                        Some((astro::utc_offset(response)?, info.tz_id))
+                    });
+                cached
+            });
+            let (offset, tz_id) = match cached {
+                Some((offset, tz_id)) => (offset, tz_id.or_else(|| Some(format!("UTC{offset}")))),
+                None => {
+                    let offset = astro::solar_offset(lon);
+                    (offset, Some(format!("UTC{offset}, from the longitude")))
+                }
+            };
+            let astronomy = astro::Astronomy::calculate(date, lat, lon, offset);
+            let location = models::LocationInfo {
+                lat: Some(lat),
+                lon: Some(lon),
+                tz_id,
+                ..Default::default()
+            };
+            output::print_astronomy(&astronomy, location, args.format);
+            return;
+        }
+        Some(Command::Config {
+            action: ConfigAction::Show { origin },
+        }) => {
+            let flags = flags(&args, &locations::load(&working_directory));
+            config::print(&config::resolve(&working_directory, &flags), *origin);
+            return;
+        }
+        None => {}
     }
 
+    let api_key = args.api_key.clone().or_else(|| load_api_key(&working_directory)).expect(
+        "TODO: implement config file, failed to grab api_key. An api key can be specified with the -a option",
+    );
+    let mut api = Api::new(api_key.clone());
+
+    let named_locations = locations::load(&working_directory);
+    let configuration = config::resolve(&working_directory, &flags(&args, &named_locations));
     if args.save_to_config {
-        save_to_config(
-            &working_directory,
-            daemon_config.clone(),
-            api_config.clone(),
-            api_key,
-        );
+        save_to_config(&working_directory, &configuration, api_key);
+    }
+    let config::Configuration {
+        api: mut api_config,
+        daemon: daemon_config,
+        ..
+    } = configuration;
+
+    if let Some(from) = args.date.or(args.from) {
+        let to = args.to.unwrap_or(from);
+        match DateRange::new(from, to, chrono::Local::now().date_naive()) {
+            Ok(range) => {
+                api_config.dt = Some(range);
+                api_config.requests.current = false;
+                api_config.requests.forecast = false;
+            }
+            Err(e) => {
+                eprintln!("Invalid dates: {e}");
+                std::process::exit(2);
+            }
+        }
     }
 
-    let response = api.make_request(&api_config);
-    if let Some(Err(e)) = response.current {
-        println!("Get Current Weather Api Call failed with: {e:?}");
-    };
-    if let Some(Err(e)) = response.alerts {
-        println!("Get Weather Alerts Api Call failed with: {e:?}");
+    if args.charts || args.hourly.is_some() || args.advice {
+        api_config.hour = Some(true);
     }
-    if let Some(Err(e)) = response.forecast {
-        println!("Get Forecast Api Call failed with: {e:?}");
+
+    if args.all {
+        if named_locations.is_empty() {
+            eprintln!("No locations to compare, add some to {}", locations::LOCATIONS_FILE);
+            std::process::exit(2);
+        }
+        let mut exit_code = 0;
+        let mut results = Vec::new();
+        for location in &named_locations {
+            let mut api = api.with_key();
+            let response = api.make_request(&location.api_config(&api_config).located());
+            let failures = [
+                ("current", "Get Current Weather", response.current),
+                ("alerts", "Get Weather Alerts", response.alerts),
+                ("forecast", "Get Forecast", response.forecast),
+                ("marine", "Get Marine Forecast", response.marine),
+                ("dated", "Get Weather For Dates", response.dated),
+            ];
+            for (request, description, result) in failures {
+                if let Some(Err(e)) = result {
+                    let description = format!("{}: {description}", location.name);
+                    output::print_error(request, &description, &e, args.format);
+                    if exit_code == 0 {
+                        exit_code = e.exit_code();
+                    }
+                }
+            }
+            results.push((location.name.clone(), api));
+        }
+        output::print_comparison(&results, args.format, &display_units);
+        std::process::exit(exit_code);
     }
 
-    if let Some((response, _timestamp)) = api.get_cached_current() {
-        println!("{:=^32}", "Current Weather");
-        println!(
-            "Current Tempurature (Imperial): {}°F, Feels like: {}°F",
-            response["current"]["temp_f"], response["current"]["feelslike_f"]
-        );
-        println!(
-            "Current Tempurature (Metric): {}°C, Feels like: {}°C",
-            response["current"]["temp_c"], response["current"]["feelslike_c"]
-        );
-        println!(
-            "Wind Speed (Imperial): {} mph, from {}",
-            response["current"]["wind_mph"], response["current"]["wind_dir"]
-        );
-        println!(
-            "Wind Speed (Metric): {} kph, from {}",
-            response["current"]["wind_kph"], response["current"]["wind_dir"]
-        );
-        println!(
-            "Wind Chill (Imperial): {}°F",
-            response["current"]["windchill_f"]
-        );
-        println!(
-            "Wind Chill (Metric): {}°C",
-            response["current"]["windchill_c"]
-        );
-        println!("Humidity: {}%", response["current"]["humidity"]);
-        println!(
-            "Pressure (Imperial): {}in",
-            response["current"]["pressure_in"]
-        );
-        println!(
-            "Pressure (Metric): {}mb",
-            response["current"]["pressure_mb"]
-        );
-        println!("Condition: {}\n", response["current"]["condition"]["text"]);
+    let mut exit_code = 0;
+    let response = api.make_request(&api_config.located());
+    let failures = [
+        ("current", "Get Current Weather", response.current),
+        ("alerts", "Get Weather Alerts", response.alerts),
+        ("forecast", "Get Forecast", response.forecast),
+        ("marine", "Get Marine Forecast", response.marine),
+        ("dated", "Get Weather For Dates", response.dated),
+    ];
+    let mut failed = Vec::new();
+    for (request, description, result) in failures {
+        if let Some(Err(e)) = result {
+            output::print_error(request, description, &e, args.format);
+            if exit_code == 0 {
+                exit_code = e.exit_code();
+            }
+            failed.push(request);
+        }
     }
 
-    if let Some((response, _timestamp)) = api.get_cached_alerts() {
-        let alert_array = &response["alerts"]["alert"];
-        if !alert_array.is_array() {
-            panic!("Received Invalid API alert response");
+    if !failed.contains(&"current") {
+        output::print_current(&api, args.format, &display_units);
+    }
+    if !failed.contains(&"alerts") {
+        output::print_alerts(&api, args.format);
+    }
+    if !failed.contains(&"forecast") {
+        output::print_forecast(&api, args.format, &display_units);
+        if let Some(count) = args.hourly {
+            output::print_hourly(&api, args.format, count, &display_units);
         }
-        for alert in (0..).map(|i| &alert_array[i]) {
-            if alert.is_null() {
-                break;
+        if args.charts && args.format == output::OutputFormat::Text {
+            if let Some((response, _timestamp)) = api.get_cached_forecast() {
+                chart::print_hourly(&models::Hour::upcoming(response, 48), args.ascii, &display_units);
+                chart::print_daily(&models::ForecastDay::list_from_json(response), args.ascii, &display_units);
             }
-
-            println!("{}", alert["headline"]);
         }
     }
 
-    if let Some((response, _timestamp)) = api.get_cached_forecast() {
-        println!("{:=^32}", "Forecast");
-        for i in 0..api_config.days.unwrap() {
-            println!(
-                "Weather in {} day(s)", i + 1
-            );
-            println!(
-                "Tempurature Average: {}°F, {}°C",
-                response["forecast"]["forecastday"][i]["day"]["avgtemp_f"],
-                response["forecast"]["forecastday"][i]["day"]["avgtemp_c"]
-            );
-            println!(
-                "Tempurature High: {}°F, {}°C",
-                response["forecast"]["forecastday"][i]["day"]["maxtemp_f"],
-                response["forecast"]["forecastday"][i]["day"]["maxtemp_c"]
-            );
-            println!(
-                "Tempurature Low: {}°F, {}°C",
-                response["forecast"]["forecastday"][i]["day"]["mintemp_f"],
-                response["forecast"]["forecastday"][i]["day"]["mintemp_c"]
-            );
-            println!(
-                "Max Wind Speed: {} mph, {} kph",
-                response["forecast"]["forecastday"][i]["day"]["maxwind_mph"],
-                response["forecast"]["forecastday"][i]["day"]["maxwind_kph"]
-            );
-            println!(
-                "Average Humidity: {}%", 
-                response["forecast"]["forecastday"][i]["day"]["avghumidity"]
-            );
-            println!(
-                "Chance of Rain: {}%",
-                response["forecast"]["forecastday"][i]["day"]["daily_chance_of_rain"]
-            );
-            println!(
-                "Chance of Snow: {}%",
-                response["forecast"]["forecastday"][i]["day"]["daily_chance_of_snow"]
-            );
-            println!(
-                "Total Precipitation: {} in, {} mm",
-                response["forecast"]["forecastday"][i]["day"]["totalprecip_in"],
-                response["forecast"]["forecastday"][i]["day"]["totalprecip_mm"]
-            );
-            println!(
-                "Condition: {}\n", 
-                response["forecast"]["forecastday"][i]["day"]["condition"]["text"]
-            );
+    if args.advice && !failed.contains(&"forecast") {
+        let mut facts = facts::Facts::from_api(&api);
+        display_units.add_facts(&mut facts);
+        let advice = advisories::advise(&advisories::load(&working_directory), &mut facts);
+        output::print_advisories(&api, args.format, &advice);
+    }
+
+    if !failed.contains(&"marine") {
+        output::print_marine(&api, args.format, &display_units);
+    }
+    if let Some(range) = &api_config.dt {
+        if !failed.contains(&"dated") {
+            output::print_dated(&api, args.format, range, &display_units);
         }
     }
 
@@ -221,8 +742,7 @@ fn main() {
     if args.daemonize {
         let config = DaemonConfiguration {
             working_directory,
-            exec_interval: args.daemon_update_interval.into(),
-            notif_interval: args.daemon_notif_interval.into(),
+            ..daemon_config.clone()
         };
 
         match daemon::daemonize(&config) {
@@ -246,16 +766,16 @@ fn main() {
                 eprintln!("Can't start daemon when one already exists")
             }
         }
-        daemon_main(api, daemon_config, api_config)
+        daemon_main(api, daemon_config, api_config, display_units)
+    }
+
+    if exit_code != 0 {
+        std::process::exit(exit_code);
     }
 }
 
-fn save_to_config(
-    working_directory: &PathBuf,
-    daemon_config: DaemonConfiguration,
-    api_config: ApiRequestConfiguration,
-    api_key: String,
-) {
+// Saves the key and the settings given as flags, the files keep everything else they had
+fn save_to_config(working_directory: &Path, configuration: &config::Configuration, api_key: String) {
     let Ok(mut file) = File::create(working_directory.join("api_key.ron")) else {
         eprintln!(
             "Failed to create file api_key.ron in {}",
@@ -269,36 +789,23 @@ fn save_to_config(
             working_directory.to_str().unwrap()
         );
     };
-    let Ok(mut file) = File::create(working_directory.join("api_config.ron")) else {
-        eprintln!(
-            "Failed to create file api_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
-        return;
-    };
-    if let Err(_e) = write!(&mut file, "{}", ron::to_string(&api_config).unwrap()) {
-        eprintln!(
-            "Failed to write to api_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
-    };
 
-    let Ok(mut file) = File::create(working_directory.join("daemon_config.ron")) else {
-        eprintln!(
-            "Failed to create file daemon_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
-        return;
-    };
-    if let Err(_e) = write!(&mut file, "{}", ron::to_string(&daemon_config).unwrap()) {
-        eprintln!(
-            "Failed to write to daemon_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
-    };
+    let given: Vec<&str> = configuration
+        .origins
+        .iter()
+        .filter(|(_, origin)| matches!(origin, config::Origin::Flag(_)))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if let Err(e) = config::save(working_directory, configuration, &given) {
+        eprintln!("Failed to save settings: {e}");
+    }
 }
 
-fn load_api_key(working_directory: &PathBuf) -> Option<String> {
+// WEATHD_API_KEY, then api_key.ron, for when there is no -a
+fn load_api_key(working_directory: &Path) -> Option<String> {
+    if let Ok(key) = std::env::var("WEATHD_API_KEY") {
+        return Some(key);
+    }
     let api_key_path = working_directory.join("api_key.ron");
     match std::fs::read_to_string(&api_key_path) {
         Ok(key) => Some(key),
@@ -313,32 +820,43 @@ fn load_api_key(working_directory: &PathBuf) -> Option<String> {
     }
 }
 
-fn load_from_config(
-    working_directory: &PathBuf,
-    daemon_config: &mut DaemonConfiguration,
-    api_config: &mut ApiRequestConfiguration,
-) {
-    if let Ok(data) = read(&working_directory.join("api_config.ron")) {
-        match ron::from_str::<ApiRequestConfiguration>(&String::from_utf8_lossy(&data)) {
-            Ok(config) => *api_config = config,
-            Err(e) => eprintln!("Failed to parse api_config.ron: {:?}", e),
+// Where the daemon caches responses for a location from locations.ron, or the
+// working directory itself for the one in api_config.ron
+fn location_directory(working_directory: &Path, name: Option<&str>) -> PathBuf {
+    let Some(name) = name else {
+        return working_directory.to_path_buf();
+    };
+    match locations::find(&locations::load(working_directory), name) {
+        Some(location) => location.directory(working_directory),
+        None => {
+            eprintln!("There is no location named {name} in {}", locations::LOCATIONS_FILE);
+            std::process::exit(2);
         }
-    } else {
-        eprintln!(
-            "Failed to open file api_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
     }
-    // Load Daemon configuration
-    if let Ok(data) = read(&working_directory.join("daemon_config.ron")) {
-        match ron::from_str::<DaemonConfiguration>(&String::from_utf8_lossy(&data)) {
-            Ok(config) => *daemon_config = config,
-            Err(e) => eprintln!("Failed to parse daemon_config.ron: {:?}", e),
+}
+
+// Uses --pick when given, the only result when there is one, and otherwise asks
+fn pick_place(places: &[models::Place], pick: Option<usize>) -> Option<&models::Place> {
+    let choice = match (pick, places.len()) {
+        (_, 0) => return None,
+        (Some(pick), _) => pick,
+        (None, 1) => 1,
+        (None, count) => {
+            print!("Pick a place to save [1-{count}], or nothing to cancel: ");
+            std::io::stdout().flush().ok()?;
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).ok()?;
+            let line = line.trim();
+            if line.is_empty() {
+                return None;
+            }
+            line.parse().unwrap_or(0)
         }
-    } else {
-        eprintln!(
-            "Failed to open file daemon_config.ron in {}",
-            working_directory.to_str().unwrap()
-        );
+    };
+    let place = choice.checked_sub(1).and_then(|i| places.get(i));
+    if place.is_none() {
+        eprintln!("There is no result {choice}");
+        std::process::exit(2);
     }
+    place
 }
@@ -0,0 +1,472 @@
+use json::JsonValue;
+use serde::{Deserialize, Serialize};
+
+// Typed views of the WeatherAPI responses. These are what the machine readable
+// output formats serialize, so renaming or removing a field needs a SCHEMA_VERSION bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn number(value: &JsonValue) -> Option<f64> {
+    value.as_f64()
+}
+
+fn text(value: &JsonValue) -> Option<String> {
+    value.as_str().map(String::from)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LocationInfo {
+    pub name: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub tz_id: Option<String>,
+    pub localtime: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Current {
+    pub last_updated: Option<String>,
+    pub last_updated_epoch: Option<i64>,
+    pub temp_c: Option<f64>,
+    pub temp_f: Option<f64>,
+    pub feelslike_c: Option<f64>,
+    pub feelslike_f: Option<f64>,
+    pub wind_kph: Option<f64>,
+    pub wind_mph: Option<f64>,
+    pub wind_degree: Option<f64>,
+    pub wind_dir: Option<String>,
+    pub gust_kph: Option<f64>,
+    pub gust_mph: Option<f64>,
+    pub windchill_c: Option<f64>,
+    pub windchill_f: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pressure_mb: Option<f64>,
+    pub pressure_in: Option<f64>,
+    pub precip_mm: Option<f64>,
+    pub precip_in: Option<f64>,
+    pub cloud: Option<f64>,
+    pub uv: Option<f64>,
+    pub vis_km: Option<f64>,
+    pub vis_miles: Option<f64>,
+    pub is_day: Option<bool>,
+    pub condition: Option<String>,
+    pub condition_code: Option<i64>,
+    pub air_quality: AirQuality,
+    pub pollen: Pollen,
+}
+
+// Concentrations in μg/m³. The indices are the provider's bands, 1 to 6 for US EPA
+// and 1 to 10 for UK DEFRA, us_aqi is the 0 to 500 number people usually mean by AQI.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AirQuality {
+    pub co: Option<f64>,
+    pub no2: Option<f64>,
+    pub o3: Option<f64>,
+    pub so2: Option<f64>,
+    pub pm2_5: Option<f64>,
+    pub pm10: Option<f64>,
+    pub us_epa_index: Option<f64>,
+    pub gb_defra_index: Option<f64>,
+    pub us_aqi: Option<f64>,
+}
+
+// Grains per m³, only sent on plans that include pollen
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Pollen {
+    pub hazel: Option<f64>,
+    pub alder: Option<f64>,
+    pub birch: Option<f64>,
+    pub oak: Option<f64>,
+    pub grass: Option<f64>,
+    pub mugwort: Option<f64>,
+    pub ragweed: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Alert {
+    pub headline: Option<String>,
+    pub event: Option<String>,
+    pub severity: Option<String>,
+    pub urgency: Option<String>,
+    pub certainty: Option<String>,
+    pub category: Option<String>,
+    pub areas: Option<String>,
+    pub effective: Option<String>,
+    pub expires: Option<String>,
+    pub desc: Option<String>,
+    pub instruction: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ForecastDay {
+    pub date: Option<String>,
+    pub maxtemp_c: Option<f64>,
+    pub maxtemp_f: Option<f64>,
+    pub mintemp_c: Option<f64>,
+    pub mintemp_f: Option<f64>,
+    pub avgtemp_c: Option<f64>,
+    pub avgtemp_f: Option<f64>,
+    pub maxwind_kph: Option<f64>,
+    pub maxwind_mph: Option<f64>,
+    pub totalprecip_mm: Option<f64>,
+    pub totalprecip_in: Option<f64>,
+    pub avghumidity: Option<f64>,
+    pub daily_chance_of_rain: Option<f64>,
+    pub daily_chance_of_snow: Option<f64>,
+    pub uv: Option<f64>,
+    pub condition: Option<String>,
+    pub air_quality: AirQuality,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Hour {
+    pub time: Option<String>,
+    pub time_epoch: Option<i64>,
+    pub temp_c: Option<f64>,
+    pub temp_f: Option<f64>,
+    pub feelslike_c: Option<f64>,
+    pub feelslike_f: Option<f64>,
+    pub chance_of_rain: Option<f64>,
+    pub chance_of_snow: Option<f64>,
+    pub will_it_rain: Option<bool>,
+    pub will_it_snow: Option<bool>,
+    pub precip_mm: Option<f64>,
+    pub precip_in: Option<f64>,
+    pub wind_kph: Option<f64>,
+    pub wind_mph: Option<f64>,
+    pub wind_degree: Option<f64>,
+    pub wind_dir: Option<String>,
+    pub gust_kph: Option<f64>,
+    pub gust_mph: Option<f64>,
+    pub humidity: Option<f64>,
+    pub cloud: Option<f64>,
+    pub uv: Option<f64>,
+    pub vis_km: Option<f64>,
+    pub vis_miles: Option<f64>,
+    pub is_day: Option<bool>,
+    pub condition: Option<String>,
+    pub air_quality: AirQuality,
+}
+
+// One hour of the marine forecast. Heights are significant wave height and swell
+// height, directions are where the swell comes from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MarineHour {
+    pub time: Option<String>,
+    pub time_epoch: Option<i64>,
+    pub sig_ht_mt: Option<f64>,
+    pub swell_ht_mt: Option<f64>,
+    pub swell_ht_ft: Option<f64>,
+    pub swell_dir: Option<f64>,
+    pub swell_dir_16_point: Option<String>,
+    pub swell_period_secs: Option<f64>,
+    pub water_temp_c: Option<f64>,
+    pub water_temp_f: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Tide {
+    pub time: Option<String>,
+    pub height_mt: Option<f64>,
+    pub kind: Option<String>,
+}
+
+// A search result, the id can be used as Location::SearchID
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Place {
+    pub id: Option<usize>,
+    pub name: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+}
+
+impl Place {
+    pub fn from_json(place: &JsonValue) -> Self {
+        Self {
+            id: place["id"].as_usize(),
+            name: text(&place["name"]),
+            region: text(&place["region"]),
+            country: text(&place["country"]),
+            lat: number(&place["lat"]),
+            lon: number(&place["lon"]),
+        }
+    }
+
+    pub fn list_from_json(response: &JsonValue) -> Vec<Self> {
+        response.members().map(Self::from_json).collect()
+    }
+}
+
+impl LocationInfo {
+    pub fn from_json(response: &JsonValue) -> Self {
+        let location = &response["location"];
+        Self {
+            name: text(&location["name"]),
+            region: text(&location["region"]),
+            country: text(&location["country"]),
+            lat: number(&location["lat"]),
+            lon: number(&location["lon"]),
+            tz_id: text(&location["tz_id"]),
+            localtime: text(&location["localtime"]),
+        }
+    }
+}
+
+impl Current {
+    pub fn from_json(response: &JsonValue) -> Self {
+        let current = &response["current"];
+        Self {
+            last_updated: text(&current["last_updated"]),
+            last_updated_epoch: current["last_updated_epoch"].as_i64(),
+            temp_c: number(&current["temp_c"]),
+            temp_f: number(&current["temp_f"]),
+            feelslike_c: number(&current["feelslike_c"]),
+            feelslike_f: number(&current["feelslike_f"]),
+            wind_kph: number(&current["wind_kph"]),
+            wind_mph: number(&current["wind_mph"]),
+            wind_degree: number(&current["wind_degree"]),
+            wind_dir: text(&current["wind_dir"]),
+            gust_kph: number(&current["gust_kph"]),
+            gust_mph: number(&current["gust_mph"]),
+            windchill_c: number(&current["windchill_c"]),
+            windchill_f: number(&current["windchill_f"]),
+            humidity: number(&current["humidity"]),
+            pressure_mb: number(&current["pressure_mb"]),
+            pressure_in: number(&current["pressure_in"]),
+            precip_mm: number(&current["precip_mm"]),
+            precip_in: number(&current["precip_in"]),
+            cloud: number(&current["cloud"]),
+            uv: number(&current["uv"]),
+            vis_km: number(&current["vis_km"]),
+            vis_miles: number(&current["vis_miles"]),
+            is_day: current["is_day"].as_u8().map(|is_day| is_day == 1),
+            condition: text(&current["condition"]["text"]),
+            condition_code: current["condition"]["code"].as_i64(),
+            air_quality: AirQuality::from_json(&current["air_quality"]),
+            pollen: Pollen::from_json(&current["pollen"]),
+        }
+    }
+}
+
+// US EPA breakpoints as (concentration low, high, index low, high)
+const PM2_5_BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 9.0, 0.0, 50.0),
+    (9.1, 35.4, 51.0, 100.0),
+    (35.5, 55.4, 101.0, 150.0),
+    (55.5, 125.4, 151.0, 200.0),
+    (125.5, 225.4, 201.0, 300.0),
+    (225.5, 325.4, 301.0, 500.0),
+];
+const PM10_BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 54.0, 0.0, 50.0),
+    (55.0, 154.0, 51.0, 100.0),
+    (155.0, 254.0, 101.0, 150.0),
+    (255.0, 354.0, 151.0, 200.0),
+    (355.0, 424.0, 201.0, 300.0),
+    (425.0, 604.0, 301.0, 500.0),
+];
+
+fn sub_index(concentration: f64, breakpoints: &[(f64, f64, f64, f64)]) -> f64 {
+    let &(low, high, index_low, index_high) = breakpoints
+        .iter()
+        .find(|(_, high, _, _)| concentration <= *high)
+        .unwrap_or(&breakpoints[breakpoints.len() - 1]);
+    let concentration = concentration.clamp(low, high);
+    ((index_high - index_low) / (high - low) * (concentration - low) + index_low).round()
+}
+
+impl AirQuality {
+    pub fn from_json(air_quality: &JsonValue) -> Self {
+        let pm2_5 = number(&air_quality["pm2_5"]);
+        let pm10 = number(&air_quality["pm10"]);
+        Self {
+            co: number(&air_quality["co"]),
+            no2: number(&air_quality["no2"]),
+            o3: number(&air_quality["o3"]),
+            so2: number(&air_quality["so2"]),
+            pm2_5,
+            pm10,
+            us_epa_index: number(&air_quality["us-epa-index"]),
+            gb_defra_index: number(&air_quality["gb-defra-index"]),
+            us_aqi: Self::us_aqi(pm2_5, pm10),
+        }
+    }
+
+    // The higher of the particulate sub-indices, after EPA's truncation to 0.1 and 1 μg/m³.
+    // EPA averages over 24 hours, the provider's readings are hourly so this runs a
+    // little jumpier than official AQI.
+    pub fn us_aqi(pm2_5: Option<f64>, pm10: Option<f64>) -> Option<f64> {
+        let pm2_5 = pm2_5.map(|c| sub_index((c * 10.0).trunc() / 10.0, &PM2_5_BREAKPOINTS));
+        let pm10 = pm10.map(|c| sub_index(c.trunc(), &PM10_BREAKPOINTS));
+        match (pm2_5, pm10) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+impl Pollen {
+    pub fn from_json(pollen: &JsonValue) -> Self {
+        Self {
+            hazel: number(&pollen["Hazel"]),
+            alder: number(&pollen["Alder"]),
+            birch: number(&pollen["Birch"]),
+            oak: number(&pollen["Oak"]),
+            grass: number(&pollen["Grass"]),
+            mugwort: number(&pollen["Mugwort"]),
+            ragweed: number(&pollen["Ragweed"]),
+        }
+    }
+}
+
+impl Alert {
+    pub fn from_json(alert: &JsonValue) -> Self {
+        Self {
+            headline: text(&alert["headline"]),
+            event: text(&alert["event"]),
+            severity: text(&alert["severity"]),
+            urgency: text(&alert["urgency"]),
+            certainty: text(&alert["certainty"]),
+            category: text(&alert["category"]),
+            areas: text(&alert["areas"]),
+            effective: text(&alert["effective"]),
+            expires: text(&alert["expires"]),
+            desc: text(&alert["desc"]),
+            instruction: text(&alert["instruction"]),
+        }
+    }
+
+    pub fn list_from_json(response: &JsonValue) -> Vec<Self> {
+        response["alerts"]["alert"]
+            .members()
+            .map(Self::from_json)
+            .collect()
+    }
+}
+
+impl ForecastDay {
+    pub fn from_json(forecastday: &JsonValue) -> Self {
+        let day = &forecastday["day"];
+        Self {
+            date: text(&forecastday["date"]),
+            maxtemp_c: number(&day["maxtemp_c"]),
+            maxtemp_f: number(&day["maxtemp_f"]),
+            mintemp_c: number(&day["mintemp_c"]),
+            mintemp_f: number(&day["mintemp_f"]),
+            avgtemp_c: number(&day["avgtemp_c"]),
+            avgtemp_f: number(&day["avgtemp_f"]),
+            maxwind_kph: number(&day["maxwind_kph"]),
+            maxwind_mph: number(&day["maxwind_mph"]),
+            totalprecip_mm: number(&day["totalprecip_mm"]),
+            totalprecip_in: number(&day["totalprecip_in"]),
+            avghumidity: number(&day["avghumidity"]),
+            daily_chance_of_rain: number(&day["daily_chance_of_rain"]),
+            daily_chance_of_snow: number(&day["daily_chance_of_snow"]),
+            uv: number(&day["uv"]),
+            condition: text(&day["condition"]["text"]),
+            air_quality: AirQuality::from_json(&day["air_quality"]),
+        }
+    }
+
+    pub fn list_from_json(response: &JsonValue) -> Vec<Self> {
+        response["forecast"]["forecastday"]
+            .members()
+            .map(Self::from_json)
+            .collect()
+    }
+}
+
+impl Hour {
+    pub fn from_json(hour: &JsonValue) -> Self {
+        let flag = |value: &JsonValue| value.as_u8().map(|flag| flag == 1);
+        Self {
+            time: text(&hour["time"]),
+            time_epoch: hour["time_epoch"].as_i64(),
+            temp_c: number(&hour["temp_c"]),
+            temp_f: number(&hour["temp_f"]),
+            feelslike_c: number(&hour["feelslike_c"]),
+            feelslike_f: number(&hour["feelslike_f"]),
+            chance_of_rain: number(&hour["chance_of_rain"]),
+            chance_of_snow: number(&hour["chance_of_snow"]),
+            will_it_rain: flag(&hour["will_it_rain"]),
+            will_it_snow: flag(&hour["will_it_snow"]),
+            precip_mm: number(&hour["precip_mm"]),
+            precip_in: number(&hour["precip_in"]),
+            wind_kph: number(&hour["wind_kph"]),
+            wind_mph: number(&hour["wind_mph"]),
+            wind_degree: number(&hour["wind_degree"]),
+            wind_dir: text(&hour["wind_dir"]),
+            gust_kph: number(&hour["gust_kph"]),
+            gust_mph: number(&hour["gust_mph"]),
+            humidity: number(&hour["humidity"]),
+            cloud: number(&hour["cloud"]),
+            uv: number(&hour["uv"]),
+            vis_km: number(&hour["vis_km"]),
+            vis_miles: number(&hour["vis_miles"]),
+            is_day: flag(&hour["is_day"]),
+            condition: text(&hour["condition"]["text"]),
+            air_quality: AirQuality::from_json(&hour["air_quality"]),
+        }
+    }
+
+    // The hours from the current one onwards
+    pub fn upcoming(response: &JsonValue, count: usize) -> Vec<Self> {
+        upcoming_hours(response, count).map(Self::from_json).collect()
+    }
+}
+
+impl MarineHour {
+    pub fn from_json(hour: &JsonValue) -> Self {
+        Self {
+            time: text(&hour["time"]),
+            time_epoch: hour["time_epoch"].as_i64(),
+            sig_ht_mt: number(&hour["sig_ht_mt"]),
+            swell_ht_mt: number(&hour["swell_ht_mt"]),
+            swell_ht_ft: number(&hour["swell_ht_ft"]),
+            swell_dir: number(&hour["swell_dir"]),
+            swell_dir_16_point: text(&hour["swell_dir_16_point"]),
+            swell_period_secs: number(&hour["swell_period_secs"]),
+            water_temp_c: number(&hour["water_temp_c"]),
+            water_temp_f: number(&hour["water_temp_f"]),
+        }
+    }
+
+    pub fn upcoming(response: &JsonValue, count: usize) -> Vec<Self> {
+        upcoming_hours(response, count).map(Self::from_json).collect()
+    }
+}
+
+impl Tide {
+    // WeatherAPI sends tide heights as strings
+    pub fn from_json(tide: &JsonValue) -> Self {
+        Self {
+            time: text(&tide["tide_time"]),
+            height_mt: number(&tide["tide_height_mt"])
+                .or_else(|| tide["tide_height_mt"].as_str()?.parse().ok()),
+            kind: text(&tide["tide_type"]),
+        }
+    }
+
+    // Every tide of every forecast day, in order
+    pub fn list_from_json(response: &JsonValue) -> Vec<Self> {
+        response["forecast"]["forecastday"]
+            .members()
+            .flat_map(|forecastday| forecastday["day"]["tides"].members())
+            .flat_map(|tides| tides["tide"].members())
+            .map(Self::from_json)
+            .collect()
+    }
+}
+
+// The raw hour objects of every forecast day from the current hour onwards
+pub fn upcoming_hours(response: &JsonValue, count: usize) -> impl Iterator<Item = &JsonValue> {
+    let now = chrono::Utc::now().timestamp();
+    response["forecast"]["forecastday"]
+        .members()
+        .flat_map(|forecastday| forecastday["hour"].members())
+        .filter(move |hour| hour["time_epoch"].as_i64().is_some_and(|epoch| epoch + 3600 > now))
+        .take(count)
+}
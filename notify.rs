@@ -0,0 +1,286 @@
+use crate::quiet::{DoNotDisturb, Severity};
+use notify_rust::{Notification, Timeout};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+// Condition icons are written here on start, and can be swapped for others of the same name
+pub const ICONS_DIRECTORY: &str = "icons";
+
+pub const SNOOZE_ACTION: &str = "snooze";
+pub const FORECAST_ACTION: &str = "forecast";
+
+// How long the snooze button snoozes for
+pub const SNOOZE_BUTTON: Duration = Duration::from_secs(3600);
+
+const ICONS: [(&str, &str); 8] = [
+    (
+        "sunny",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><circle cx="32" cy="32" r="13" fill="#f6b40e"/><g stroke="#f6b40e" stroke-width="4" stroke-linecap="round"><path d="M32 4v8M32 52v8M4 32h8M52 32h8M12 12l6 6M46 46l6 6M12 52l6-6M46 18l6-6"/></g></svg>"##,
+    ),
+    (
+        "clear-night",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M40 8a24 24 0 1 0 16 40A20 20 0 0 1 40 8z" fill="#c9d3e6"/></svg>"##,
+    ),
+    (
+        "cloudy",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M18 48a10 10 0 0 1 0-20 14 14 0 0 1 27-3 11 11 0 0 1 1 23z" fill="#9aa5b1"/></svg>"##,
+    ),
+    (
+        "rain",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M18 38a10 10 0 0 1 0-20 14 14 0 0 1 27-3 11 11 0 0 1 1 23z" fill="#7b8794"/><g stroke="#2f80ed" stroke-width="4" stroke-linecap="round"><path d="M22 44l-4 10M34 44l-4 10M46 44l-4 10"/></g></svg>"##,
+    ),
+    (
+        "snow",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M18 38a10 10 0 0 1 0-20 14 14 0 0 1 27-3 11 11 0 0 1 1 23z" fill="#9aa5b1"/><g fill="#8ecae6"><circle cx="20" cy="48" r="4"/><circle cx="32" cy="54" r="4"/><circle cx="44" cy="48" r="4"/></g></svg>"##,
+    ),
+    (
+        "thunder",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M18 38a10 10 0 0 1 0-20 14 14 0 0 1 27-3 11 11 0 0 1 1 23z" fill="#52606d"/><path d="M34 36l-10 14h8l-4 12 14-18h-8l4-8z" fill="#f6b40e"/></svg>"##,
+    ),
+    (
+        "fog",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><g stroke="#9aa5b1" stroke-width="5" stroke-linecap="round"><path d="M10 22h44M6 32h44M14 42h44M10 52h36"/></g></svg>"##,
+    ),
+    (
+        "alert",
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><path d="M32 6l28 52H4z" fill="#e63946"/><path d="M32 24v16" stroke="#fff" stroke-width="6" stroke-linecap="round"/><circle cx="32" cy="49" r="3.5" fill="#fff"/></svg>"##,
+    ),
+];
+
+// e.g. notifications: (icons: true, actions: false)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotificationConfiguration {
+    pub icons: bool,
+    // Snooze and Show forecast buttons, where the notification server supports them
+    pub actions: bool,
+}
+
+impl Default for NotificationConfiguration {
+    fn default() -> Self {
+        Self {
+            icons: true,
+            actions: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub severity: Severity,
+    // Condition text to pick the icon from, or "alert"
+    pub icon: Option<String>,
+    // Notifications with the same key update each other in place instead of stacking
+    pub replaces: Option<String>,
+    // Offer to show the forecast for this station, None for the one in api_config.ron
+    pub forecast: Option<Option<String>>,
+}
+
+// A button pressed on one of the notifications
+#[derive(Debug, Clone)]
+pub struct Invoked {
+    pub action: String,
+    pub station: Option<String>,
+}
+
+fn timeout(severity: Severity) -> Timeout {
+    match severity {
+        Severity::Minor => Timeout::Milliseconds(5000),
+        Severity::Moderate => Timeout::Milliseconds(10000),
+        Severity::Severe | Severity::Extreme => Timeout::Never,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn urgency(severity: Severity) -> notify_rust::Urgency {
+    match severity {
+        Severity::Minor => notify_rust::Urgency::Low,
+        Severity::Moderate => notify_rust::Urgency::Normal,
+        Severity::Severe | Severity::Extreme => notify_rust::Urgency::Critical,
+    }
+}
+
+// Matches the provider's condition texts, like "Patchy light rain with thunder"
+fn icon_name(condition: &str) -> &'static str {
+    let condition = condition.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| condition.contains(word));
+    if condition == "alert" {
+        "alert"
+    } else if has(&["thunder"]) {
+        "thunder"
+    } else if has(&["snow", "blizzard", "sleet", "ice"]) {
+        "snow"
+    } else if has(&["rain", "drizzle", "shower"]) {
+        "rain"
+    } else if has(&["fog", "mist", "haze"]) {
+        "fog"
+    } else if has(&["cloud", "overcast"]) {
+        "cloudy"
+    } else if has(&["clear"]) {
+        "clear-night"
+    } else {
+        "sunny"
+    }
+}
+
+// Server ids of the notifications showing buttons, with the station each one is for
+type Pending = Arc<Mutex<HashMap<u32, Option<String>>>>;
+
+// Passes on the buttons pressed on any of our notifications from one thread that lives as
+// long as the daemon, and forgets notifications once they are closed. None if it can't
+// listen to the session bus.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn listen(pending: Pending, sender: Sender<Invoked>) -> Option<()> {
+    use zbus::blocking::{Connection, MessageIterator};
+    use zbus::{message::Type, MatchRule};
+
+    let connection = Connection::session().ok()?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface("org.freedesktop.Notifications")
+        .ok()?
+        .build();
+    let messages = MessageIterator::for_match_rule(rule, &connection, None).ok()?;
+    std::thread::spawn(move || {
+        let _connection = connection;
+        for message in messages.flatten() {
+            let header = message.header();
+            match header.member().map(|member| member.as_str()) {
+                Some("ActionInvoked") => {
+                    let Ok((id, action)) = message.body().deserialize::<(u32, String)>() else {
+                        continue;
+                    };
+                    let station = pending.lock().unwrap().remove(&id);
+                    if let Some(station) = station.filter(|_| action == SNOOZE_ACTION || action == FORECAST_ACTION) {
+                        let _ = sender.send(Invoked { action, station });
+                    }
+                }
+                Some("NotificationClosed") => {
+                    if let Ok((id, _reason)) = message.body().deserialize::<(u32, u32)>() {
+                        pending.lock().unwrap().remove(&id);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    Some(())
+}
+
+// Sends the daemon's desktop notifications, holding them back while do not disturb
+// says so, and passes on the buttons pressed on them
+pub struct Notifier {
+    pub dnd: DoNotDisturb,
+    icons: Option<PathBuf>,
+    actions: bool,
+    // Server ids of the last notification sent for each replace key
+    replacing: HashMap<String, u32>,
+    pending: Pending,
+    invoked: (Sender<Invoked>, Receiver<Invoked>),
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfiguration, dnd: DoNotDisturb, working_directory: &Path) -> Self {
+        let icons = working_directory.join(ICONS_DIRECTORY);
+        let icons = config.icons.then(|| install_icons(&icons)).flatten();
+        let pending = Pending::default();
+        let invoked = mpsc::channel();
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let actions = config.actions
+            && notify_rust::get_capabilities()
+                .map(|capabilities| capabilities.iter().any(|capability| capability == "actions"))
+                .unwrap_or(false)
+            && listen(pending.clone(), invoked.0.clone()).is_some();
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let actions = false;
+        Self {
+            dnd,
+            icons,
+            actions,
+            replacing: HashMap::new(),
+            pending,
+            invoked,
+        }
+    }
+
+    fn icon(&self, condition: &str) -> Option<String> {
+        let path = self.icons.as_ref()?.join(format!("weathd-{}.svg", icon_name(condition)));
+        path.exists().then(|| path.to_string_lossy().to_string())
+    }
+
+    // Shows the message unless do not disturb holds it back for later
+    pub fn send(&mut self, message: Message) -> Result<(), notify_rust::error::Error> {
+        if !self.dnd.allows(message.severity) {
+            self.dnd.hold(&message.title);
+            return Ok(());
+        }
+        self.show(message)
+    }
+
+    // Shows the message whatever do not disturb says
+    pub fn show(&mut self, message: Message) -> Result<(), notify_rust::error::Error> {
+        let mut notification = Notification::new();
+        notification
+            .summary(&message.title)
+            .body(&message.body)
+            .timeout(timeout(message.severity));
+        if let Some(icon) = message.icon.as_deref().and_then(|condition| self.icon(condition)) {
+            notification.icon(&icon);
+        }
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            notification.urgency(urgency(message.severity));
+            if let Some(id) = message.replaces.as_ref().and_then(|key| self.replacing.get(key)) {
+                notification.id(*id);
+            }
+            if self.actions {
+                notification.action(SNOOZE_ACTION, "Snooze 1h");
+                if message.forecast.is_some() {
+                    notification.action(FORECAST_ACTION, "Show forecast");
+                }
+            }
+        }
+
+        let handle = notification.show()?;
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            if let Some(key) = message.replaces {
+                self.replacing.insert(key, handle.id());
+            }
+            if self.actions {
+                self.pending.lock().unwrap().insert(handle.id(), message.forecast.flatten());
+            }
+        }
+        drop(handle);
+        Ok(())
+    }
+
+    // Waits up to `timeout` for a button to be pressed
+    pub fn wait(&self, timeout: Duration) -> Option<Invoked> {
+        self.invoked.1.recv_timeout(timeout).ok()
+    }
+}
+
+// Writes any of the bundled icons that aren't in `directory` yet, None if it can't
+fn install_icons(directory: &Path) -> Option<PathBuf> {
+    std::fs::create_dir_all(directory).ok()?;
+    for (name, svg) in ICONS {
+        let path = directory.join(format!("weathd-{name}.svg"));
+        if !path.exists() {
+            std::fs::write(path, svg).ok()?;
+        }
+    }
+    Some(directory.to_path_buf())
+}
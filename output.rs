@@ -0,0 +1,544 @@
+use crate::advisories::Advice;
+use crate::api::*;
+use crate::astro::Astronomy;
+use crate::models::*;
+use crate::units::{with_unit, DisplayUnits};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fmt::Display,
+    time::{Instant, SystemTime},
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Ron,
+    Csv,
+    Tsv,
+}
+
+// Envelope for every machine readable document, so scripts can check what they got
+#[derive(Serialize)]
+pub struct Report<T: Serialize> {
+    pub schema: &'static str,
+    pub version: u32,
+    pub provider: &'static str,
+    pub fetched_at: String,
+    pub age_seconds: u64,
+    pub location: LocationInfo,
+    pub data: T,
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    schema: &'static str,
+    version: u32,
+    provider: &'static str,
+    request: &'static str,
+    kind: &'static str,
+    exit_code: i32,
+    message: String,
+}
+
+fn show<T: Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+fn report<T: Serialize>(schema: &'static str, response: &json::JsonValue, timestamp: &Instant, data: T) -> Report<T> {
+    let age = timestamp.elapsed();
+    let fetched_at: chrono::DateTime<chrono::Local> = SystemTime::now()
+        .checked_sub(age)
+        .unwrap_or(SystemTime::now())
+        .into();
+    Report {
+        schema,
+        version: SCHEMA_VERSION,
+        provider: PROVIDER,
+        fetched_at: fetched_at.to_rfc3339(),
+        age_seconds: age.as_secs(),
+        location: LocationInfo::from_json(response),
+        data,
+    }
+}
+
+fn emit<T: Serialize, R: Serialize>(report: &Report<T>, rows: &[R], format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string(report).unwrap()),
+        OutputFormat::Ron => println!("{}", ron::to_string(report).unwrap()),
+        OutputFormat::Csv | OutputFormat::Tsv => print_table(rows, format),
+    }
+}
+
+// Nested objects become columns named after both fields, like air_quality.pm2_5
+fn flatten(prefix: &str, fields: serde_json::Map<String, serde_json::Value>) -> serde_json::Map<String, serde_json::Value> {
+    let mut flat = serde_json::Map::new();
+    for (name, value) in fields {
+        let name = format!("{prefix}{name}");
+        match value {
+            serde_json::Value::Object(fields) => flat.extend(flatten(&format!("{name}."), fields)),
+            value => {
+                flat.insert(name, value);
+            }
+        }
+    }
+    flat
+}
+
+// One header row naming the fields, then one row per record
+fn print_table<T: Serialize>(records: &[T], format: OutputFormat) {
+    let separator = if format == OutputFormat::Tsv { "\t" } else { "," };
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = records
+        .iter()
+        .filter_map(|record| match serde_json::to_value(record) {
+            Ok(serde_json::Value::Object(fields)) => Some(flatten("", fields)),
+            _ => None,
+        })
+        .collect();
+    let Some(first) = rows.first() else {
+        return;
+    };
+
+    println!("{}", first.keys().cloned().collect::<Vec<_>>().join(separator));
+    for row in &rows {
+        let cells: Vec<String> = row
+            .values()
+            .map(|value| {
+                let cell = match value {
+                    serde_json::Value::Null => String::new(),
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                match format {
+                    OutputFormat::Tsv => cell.replace(['\t', '\n', '\r'], " "),
+                    _ if cell.contains([',', '"', '\n', '\r']) => {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    }
+                    _ => cell,
+                }
+            })
+            .collect();
+        println!("{}", cells.join(separator));
+    }
+    println!();
+}
+
+pub fn print_error(request: &'static str, description: &str, error: &ApiResponseError, format: OutputFormat) {
+    let report = ErrorReport {
+        schema: "weathd.error",
+        version: SCHEMA_VERSION,
+        provider: PROVIDER,
+        request,
+        kind: error.kind(),
+        exit_code: error.exit_code(),
+        message: error.message(),
+    };
+    match format {
+        OutputFormat::Json => eprintln!("{}", serde_json::to_string(&report).unwrap()),
+        OutputFormat::Ron => eprintln!("{}", ron::to_string(&report).unwrap()),
+        _ => eprintln!("{description} Api Call failed with {} error: {}", report.kind, report.message),
+    }
+}
+
+pub fn print_current(api: &Api, format: OutputFormat, units: &DisplayUnits) {
+    let Some((response, timestamp)) = api.get_cached_current() else {
+        return;
+    };
+    let current = Current::from_json(response);
+    if format != OutputFormat::Text {
+        let report = report("weathd.current", response, timestamp, current);
+        return emit(&report, std::slice::from_ref(&report.data), format);
+    }
+
+    let (temperature, speed) = (units.temperature(), units.speed());
+    println!("{:=^32}", "Current Weather");
+    println!(
+        "Current Temperature: {}, Feels like: {}",
+        with_unit(temperature, current.temp_c), with_unit(temperature, current.feelslike_c)
+    );
+    println!(
+        "Wind Speed: {}, from {}",
+        with_unit(speed, current.wind_kph), show(&current.wind_dir)
+    );
+    println!("Wind Chill: {}", with_unit(temperature, current.windchill_c));
+    println!("Humidity: {}%", show(&current.humidity));
+    println!("Pressure: {}", with_unit(units.pressure(), current.pressure_mb));
+    println!("Condition: {}", show(&current.condition));
+    print_air_quality(&current.air_quality);
+    let pollen = &current.pollen;
+    let counts = [
+        ("Hazel", pollen.hazel),
+        ("Alder", pollen.alder),
+        ("Birch", pollen.birch),
+        ("Oak", pollen.oak),
+        ("Grass", pollen.grass),
+        ("Mugwort", pollen.mugwort),
+        ("Ragweed", pollen.ragweed),
+    ];
+    if counts.iter().any(|(_, count)| count.is_some()) {
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(name, count)| format!("{name} {}", show(count)))
+            .collect();
+        println!("Pollen (grains/m³): {}", counts.join(", "));
+    }
+    println!();
+}
+
+// Nothing is printed when the provider sent no air quality
+fn print_air_quality(air_quality: &AirQuality) {
+    if air_quality.us_epa_index.is_none() && air_quality.pm2_5.is_none() {
+        return;
+    }
+    println!(
+        "Air Quality: AQI {}, US EPA index {}, UK DEFRA index {}",
+        show(&air_quality.us_aqi), show(&air_quality.us_epa_index), show(&air_quality.gb_defra_index)
+    );
+    println!(
+        "Pollutants (μg/m³): PM2.5 {}, PM10 {}, O3 {}, NO2 {}, SO2 {}, CO {}",
+        show(&air_quality.pm2_5),
+        show(&air_quality.pm10),
+        show(&air_quality.o3),
+        show(&air_quality.no2),
+        show(&air_quality.so2),
+        show(&air_quality.co)
+    );
+}
+
+pub fn print_alerts(api: &Api, format: OutputFormat) {
+    let Some((response, timestamp)) = api.get_cached_alerts() else {
+        return;
+    };
+    let alerts = Alert::list_from_json(response);
+    if format != OutputFormat::Text {
+        let report = report("weathd.alerts", response, timestamp, alerts);
+        return emit(&report, &report.data, format);
+    }
+
+    for alert in alerts {
+        println!("{}", show(&alert.headline));
+    }
+}
+
+pub fn print_forecast(api: &Api, format: OutputFormat, units: &DisplayUnits) {
+    let Some((response, timestamp)) = api.get_cached_forecast() else {
+        return;
+    };
+    let days = ForecastDay::list_from_json(response);
+    if format != OutputFormat::Text {
+        let report = report("weathd.forecast", response, timestamp, days);
+        return emit(&report, &report.data, format);
+    }
+
+    println!("{:=^32}", "Forecast");
+    for (i, day) in days.iter().enumerate() {
+        print_day(&format!("Weather in {} day(s)", i + 1), day, units);
+    }
+}
+
+fn print_day(heading: &str, day: &ForecastDay, units: &DisplayUnits) {
+    let temperature = units.temperature();
+    println!("{heading}");
+    println!("Temperature Average: {}", with_unit(temperature, day.avgtemp_c));
+    println!("Temperature High: {}", with_unit(temperature, day.maxtemp_c));
+    println!("Temperature Low: {}", with_unit(temperature, day.mintemp_c));
+    println!("Max Wind Speed: {}", with_unit(units.speed(), day.maxwind_kph));
+    println!("Average Humidity: {}%", show(&day.avghumidity));
+    println!("Chance of Rain: {}%", show(&day.daily_chance_of_rain));
+    println!("Chance of Snow: {}%", show(&day.daily_chance_of_snow));
+    println!(
+        "Total Precipitation: {}",
+        with_unit(units.precipitation(), day.totalprecip_mm)
+    );
+    println!("Condition: {}", show(&day.condition));
+    print_air_quality(&day.air_quality);
+    println!();
+}
+
+// Days asked for with --date or --from/--to, past ones are what was observed
+pub fn print_dated(api: &Api, format: OutputFormat, range: &DateRange, units: &DisplayUnits) {
+    let Some((response, timestamp)) = api.get_cached_dated() else {
+        return;
+    };
+    let days = ForecastDay::list_from_json(response);
+    let (schema, title) = match range.endpoint {
+        DateEndpoint::History => ("weathd.history", "History"),
+        DateEndpoint::Forecast | DateEndpoint::Future => ("weathd.forecast", "Forecast"),
+    };
+    if format != OutputFormat::Text {
+        let report = report(schema, response, timestamp, days);
+        return emit(&report, &report.data, format);
+    }
+
+    println!("{title:=^32}");
+    for day in &days {
+        print_day(&format!("Weather on {}", show(&day.date)), day, units);
+    }
+}
+
+pub fn print_hourly(api: &Api, format: OutputFormat, count: usize, units: &DisplayUnits) {
+    let Some((response, timestamp)) = api.get_cached_forecast() else {
+        return;
+    };
+    let hours = Hour::upcoming(response, count);
+    if format != OutputFormat::Text {
+        let report = report("weathd.hourly", response, timestamp, hours);
+        return emit(&report, &report.data, format);
+    }
+
+    let (temperature, speed) = (units.temperature(), units.speed());
+    println!("{:=^32}", "Hourly Forecast");
+    println!(
+        "{:<5} {:>7} {:>7} {:>5} {:>7} {:>13} {:>9} {:>5} {:>4} {:>7}  Condition",
+        "Time", "Temp", "Feels", "Rain", "Precip", "Wind", "Gust", "Cloud", "UV", "Vis"
+    );
+    for hour in &hours {
+        let chance = match (hour.chance_of_rain, hour.chance_of_snow) {
+            (None, None) => None,
+            (rain, snow) => Some(rain.unwrap_or(0.0).max(snow.unwrap_or(0.0))),
+        };
+        println!(
+            "{:<5} {:>7} {:>7} {:>5} {:>7} {:>13} {:>9} {:>5} {:>4} {:>7}  {}",
+            hour.time.as_deref().and_then(|time| time.get(11..)).unwrap_or("-"),
+            with_unit(temperature, hour.temp_c),
+            with_unit(temperature, hour.feelslike_c),
+            format!("{}%", show(&chance)),
+            with_unit(units.precipitation(), hour.precip_mm),
+            format!("{} {}", with_unit(speed, hour.wind_kph), hour.wind_dir.as_deref().unwrap_or("")),
+            with_unit(speed, hour.gust_kph),
+            format!("{}%", show(&hour.cloud)),
+            show(&hour.uv),
+            with_unit(units.distance(), hour.vis_km),
+            show(&hour.condition)
+        );
+    }
+    println!();
+}
+
+pub fn print_astronomy(astronomy: &Astronomy, location: LocationInfo, format: OutputFormat) {
+    if format != OutputFormat::Text {
+        let report = Report {
+            schema: "weathd.astronomy",
+            version: SCHEMA_VERSION,
+            provider: astronomy.source,
+            fetched_at: chrono::Local::now().to_rfc3339(),
+            age_seconds: 0,
+            location,
+            data: astronomy,
+        };
+        return emit(&report, std::slice::from_ref(&report.data), format);
+    }
+
+    println!("{:=^32}", "Astronomy");
+    println!("Date: {} ({})", astronomy.date, astronomy.source);
+    if let Some(tz_id) = &location.tz_id {
+        println!("Times in: {tz_id}");
+    }
+    println!("Sunrise: {}, Sunset: {}", show(&astronomy.sunrise), show(&astronomy.sunset));
+    println!("Moonrise: {}, Moonset: {}", show(&astronomy.moonrise), show(&astronomy.moonset));
+    println!(
+        "Moon Phase: {}, {}% illuminated",
+        show(&astronomy.moon_phase), show(&astronomy.moon_illumination)
+    );
+    println!(
+        "Civil Twilight: {} to {}",
+        show(&astronomy.civil_dawn), show(&astronomy.civil_dusk)
+    );
+    println!(
+        "Nautical Twilight: {} to {}",
+        show(&astronomy.nautical_dawn), show(&astronomy.nautical_dusk)
+    );
+    println!(
+        "Golden Hour: until {}, from {}\n",
+        show(&astronomy.golden_hour_morning_end), show(&astronomy.golden_hour_evening_start)
+    );
+}
+
+#[derive(Serialize)]
+struct Marine {
+    tides: Vec<Tide>,
+    hours: Vec<MarineHour>,
+}
+
+pub fn print_marine(api: &Api, format: OutputFormat, units: &DisplayUnits) {
+    let Some((response, timestamp)) = api.get_cached_marine() else {
+        return;
+    };
+    let marine = Marine {
+        tides: Tide::list_from_json(response),
+        hours: MarineHour::upcoming(response, 24),
+    };
+    if format != OutputFormat::Text {
+        let report = report("weathd.marine", response, timestamp, marine);
+        return emit(&report, &report.data.hours, format);
+    }
+
+    let height = units.height();
+    println!("{:=^32}", "Marine");
+    for tide in &marine.tides {
+        println!(
+            "{} Tide: {}, {}",
+            show(&tide.kind), show(&tide.time), with_unit(height, tide.height_mt)
+        );
+    }
+    println!(
+        "{:<5} {:>7} {:>7} {:>7} {:>7} {:>7}",
+        "Time", "Waves", "Swell", "From", "Period", "Water"
+    );
+    for hour in &marine.hours {
+        println!(
+            "{:<5} {:>7} {:>7} {:>7} {:>7} {:>7}",
+            hour.time.as_deref().and_then(|time| time.get(11..)).unwrap_or("-"),
+            with_unit(height, hour.sig_ht_mt),
+            with_unit(height, hour.swell_ht_mt),
+            show(&hour.swell_dir_16_point),
+            format!("{}s", show(&hour.swell_period_secs)),
+            with_unit(units.temperature(), hour.water_temp_c),
+        );
+    }
+    println!();
+}
+
+pub fn print_advisories(api: &Api, format: OutputFormat, advice: &[Advice]) {
+    let Some((response, timestamp)) = api.get_cached_current().or(api.get_cached_forecast()) else {
+        return;
+    };
+    if format != OutputFormat::Text {
+        let report = report("weathd.advisories", response, timestamp, advice);
+        return emit(&report, report.data, format);
+    }
+
+    println!("{:=^32}", "Advice");
+    if advice.is_empty() {
+        println!("Nothing to advise");
+    }
+    for advice in advice {
+        println!("{}: {}", advice.category, advice.message);
+    }
+    println!();
+}
+
+pub fn print_places(places: &[Place], format: OutputFormat) {
+    if format != OutputFormat::Text {
+        let report = Report {
+            schema: "weathd.search",
+            version: SCHEMA_VERSION,
+            provider: PROVIDER,
+            fetched_at: chrono::Local::now().to_rfc3339(),
+            age_seconds: 0,
+            location: LocationInfo::default(),
+            data: places,
+        };
+        return emit(&report, report.data, format);
+    }
+
+    if places.is_empty() {
+        println!("No matching places found");
+        return;
+    }
+    for (i, place) in places.iter().enumerate() {
+        let name: Vec<String> = [&place.name, &place.region, &place.country]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .cloned()
+            .collect();
+        println!(
+            "{:>2}. {} ({}, {}) id:{}",
+            i + 1,
+            name.join(", "),
+            show(&place.lat),
+            show(&place.lon),
+            show(&place.id)
+        );
+    }
+}
+
+// One row per location, for --all
+#[derive(Serialize)]
+struct Comparison {
+    location: String,
+    temp_c: Option<f64>,
+    feelslike_c: Option<f64>,
+    humidity: Option<f64>,
+    wind_kph: Option<f64>,
+    wind_dir: Option<String>,
+    condition: Option<String>,
+    maxtemp_c: Option<f64>,
+    mintemp_c: Option<f64>,
+    chance_of_rain: Option<f64>,
+    alerts: Option<usize>,
+}
+
+// A failed request leaves the error response in the cache
+fn ok(cached: Option<&(json::JsonValue, Instant)>) -> Option<&json::JsonValue> {
+    cached.map(|(response, _)| response).filter(|response| response["error"].is_null())
+}
+
+pub fn print_comparison(results: &[(String, Api)], format: OutputFormat, units: &DisplayUnits) {
+    let rows: Vec<Comparison> = results
+        .iter()
+        .map(|(name, api)| {
+            let current = ok(api.get_cached_current())
+                .map(Current::from_json)
+                .unwrap_or_default();
+            let today = ok(api.get_cached_forecast())
+                .and_then(|response| ForecastDay::list_from_json(response).into_iter().next())
+                .unwrap_or_default();
+            Comparison {
+                location: name.clone(),
+                temp_c: current.temp_c,
+                feelslike_c: current.feelslike_c,
+                humidity: current.humidity,
+                wind_kph: current.wind_kph,
+                wind_dir: current.wind_dir,
+                condition: current.condition,
+                maxtemp_c: today.maxtemp_c,
+                mintemp_c: today.mintemp_c,
+                chance_of_rain: today.daily_chance_of_rain,
+                alerts: ok(api.get_cached_alerts()).map(|response| Alert::list_from_json(response).len()),
+            }
+        })
+        .collect();
+
+    if format != OutputFormat::Text {
+        let report = Report {
+            schema: "weathd.comparison",
+            version: SCHEMA_VERSION,
+            provider: PROVIDER,
+            fetched_at: chrono::Local::now().to_rfc3339(),
+            age_seconds: 0,
+            location: LocationInfo::default(),
+            data: rows,
+        };
+        return emit(&report, &report.data, format);
+    }
+
+    let (temperature, speed) = (units.temperature(), units.speed());
+    let width = rows.iter().map(|row| row.location.chars().count()).max().unwrap_or(0).max(8);
+    println!(
+        "{:<width$} {:>7} {:>7} {:>5} {:>13} {:>15} {:>5} {:>6}  Condition",
+        "Location", "Temp", "Feels", "Hum", "Wind", "Low/High", "Rain", "Alerts"
+    );
+    for row in &rows {
+        println!(
+            "{:<width$} {:>7} {:>7} {:>5} {:>13} {:>15} {:>5} {:>6}  {}",
+            row.location,
+            with_unit(temperature, row.temp_c),
+            with_unit(temperature, row.feelslike_c),
+            format!("{}%", show(&row.humidity)),
+            format!("{} {}", with_unit(speed, row.wind_kph), row.wind_dir.as_deref().unwrap_or("")),
+            format!("{}/{}", with_unit(temperature, row.mintemp_c), with_unit(temperature, row.maxtemp_c)),
+            format!("{}%", show(&row.chance_of_rain)),
+            show(&row.alerts),
+            show(&row.condition)
+        );
+    }
+    println!();
+}
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// Holds the time a `snooze` ends, written by the command and read by the daemon
+pub const SNOOZE_FILE: &str = "snooze";
+
+// How many held back notifications are listed by name once they are let through
+const HELD_LISTED: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    #[default]
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+impl Severity {
+    // Alerts use the CAP severities, anything else counts as Minor
+    pub fn from_alert(severity: Option<&str>) -> Self {
+        match severity.map(str::to_lowercase).as_deref() {
+            Some("extreme") => Self::Extreme,
+            Some("severe") => Self::Severe,
+            Some("moderate") => Self::Moderate,
+            _ => Self::Minor,
+        }
+    }
+}
+
+// e.g. QuietWindow(days: ["Sat", "Sun"], start: "23:00:00", end: "09:00:00")
+// A window that ends before it starts runs past midnight, `days` are the days it
+// starts on and every day if left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuietWindow {
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietWindow {
+    fn contains(&self, now: NaiveDateTime) -> bool {
+        let on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        let (day, time) = (now.weekday(), now.time());
+        if self.start <= self.end {
+            on(day) && time >= self.start && time < self.end
+        } else {
+            (on(day) && time >= self.start) || (on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+// e.g. quiet: (windows: [(start: "22:00:00", end: "07:00:00")], breakthrough: Some(Severe))
+// Notifications at least as severe as `breakthrough` are sent during quiet hours and
+// snoozes anyway, None holds back everything.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct QuietConfiguration {
+    pub windows: Vec<QuietWindow>,
+    pub breakthrough: Option<Severity>,
+}
+
+impl Default for QuietConfiguration {
+    fn default() -> Self {
+        Self {
+            windows: Vec::new(),
+            breakthrough: Some(Severity::Extreme),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Held {
+    time: DateTime<Local>,
+    title: String,
+}
+
+// Decides whether the daemon may notify right now, and keeps what it may not until it can
+#[derive(Debug)]
+pub struct DoNotDisturb {
+    config: QuietConfiguration,
+    working_directory: PathBuf,
+    held: Vec<Held>,
+}
+
+impl DoNotDisturb {
+    pub fn new(config: QuietConfiguration, working_directory: PathBuf) -> Self {
+        Self {
+            config,
+            working_directory,
+            held: Vec::new(),
+        }
+    }
+
+    // Why notifications are being held back, None if they aren't
+    pub fn reason(&self) -> Option<String> {
+        let now = Local::now();
+        if let Some(until) = snoozed_until(&self.working_directory) {
+            return Some(format!("snoozed until {}", until.format("%H:%M")));
+        }
+        self.config
+            .windows
+            .iter()
+            .any(|window| window.contains(now.naive_local()))
+            .then(|| String::from("quiet hours"))
+    }
+
+    pub fn allows(&self, severity: Severity) -> bool {
+        self.config.breakthrough.is_some_and(|breakthrough| severity >= breakthrough) || self.reason().is_none()
+    }
+
+    pub fn hold(&mut self, title: &str) {
+        self.held.push(Held {
+            time: Local::now(),
+            title: title.to_string(),
+        });
+    }
+
+    pub fn holding(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    // Once notifications are allowed again, a summary of everything that was held back
+    pub fn release(&mut self) -> Option<(String, String)> {
+        if self.held.is_empty() || self.reason().is_some() {
+            return None;
+        }
+        let held = std::mem::take(&mut self.held);
+        let mut lines: Vec<String> = held
+            .iter()
+            .take(HELD_LISTED)
+            .map(|held| format!("{} {}", held.time.format("%H:%M"), held.title))
+            .collect();
+        if held.len() > HELD_LISTED {
+            lines.push(format!("and {} more", held.len() - HELD_LISTED));
+        }
+        Some((format!("{} Notifications Held Back", held.len()), lines.join("\n")))
+    }
+}
+
+#[derive(Debug)]
+pub enum SnoozeError {
+    TooLong,
+    Io(std::io::Error),
+}
+
+impl Display for SnoozeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLong => write!(f, "too long to snooze for, pick a shorter duration"),
+            Self::Io(e) => write!(f, "could not write {SNOOZE_FILE}: {e}"),
+        }
+    }
+}
+
+pub fn snoozed_until(working_directory: &Path) -> Option<DateTime<Local>> {
+    let text = std::fs::read_to_string(working_directory.join(SNOOZE_FILE)).ok()?;
+    let until = DateTime::parse_from_rfc3339(text.trim()).ok()?.with_timezone(&Local);
+    (until > Local::now()).then_some(until)
+}
+
+// Snoozes for `duration` from now, a zero duration ends the snooze instead
+pub fn snooze(working_directory: &Path, duration: Duration) -> Result<Option<DateTime<Local>>, SnoozeError> {
+    let path = working_directory.join(SNOOZE_FILE);
+    if duration.is_zero() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(SnoozeError::Io(e)),
+            _ => Ok(None),
+        };
+    }
+    let until = TimeDelta::from_std(duration)
+        .ok()
+        .and_then(|duration| Local::now().checked_add_signed(duration))
+        // RFC 3339 only has room for four digit years
+        .filter(|until| until.year() <= 9999)
+        .ok_or(SnoozeError::TooLong)?;
+    std::fs::write(path, until.to_rfc3339()).map_err(SnoozeError::Io)?;
+    Ok(Some(until))
+}
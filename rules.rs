@@ -0,0 +1,103 @@
+use crate::facts::Facts;
+use crate::quiet::Severity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum Comparison {
+    Above,
+    Below,
+    Equal,
+}
+
+impl Comparison {
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Above => value > threshold,
+            Self::Below => value < threshold,
+            Self::Equal => value == threshold,
+        }
+    }
+}
+
+// e.g. Rule(name: "Storm coming", fact: "pressure_change_mb", comparison: Below, threshold: -3.0)
+//
+// With `hours` set the fact is looked up in the hourly forecast instead, and the rule
+// matches if any of the next `hours` hours does, e.g.
+// Rule(name: "Rain soon", fact: "chance_of_rain", comparison: Above, threshold: 70.0, hours: Some(3))
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub fact: String,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub hours: Option<usize>,
+    // Only rules at least as severe as quiet.breakthrough notify during quiet hours
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl Rule {
+    fn compare(&self, facts: &Facts) -> bool {
+        let Some(value) = facts.number(&self.fact) else {
+            return false;
+        };
+        self.comparison.holds(value, self.threshold)
+    }
+
+    // The facts that satisfied the rule, either the hour that did or all of them
+    fn matching<'a>(&self, facts: &'a Facts) -> Option<&'a Facts> {
+        match self.hours {
+            Some(hours) => facts.hours.iter().take(hours).find(|hour| self.compare(hour)),
+            None => self.compare(facts).then_some(facts),
+        }
+    }
+
+    pub fn matches(&self, facts: &Facts) -> bool {
+        self.matching(facts).is_some()
+    }
+
+    pub fn message(&self, facts: &Facts) -> String {
+        let facts = self.matching(facts).unwrap_or(facts);
+        let at = match (self.hours, facts.get("time")) {
+            (Some(_), Some(time)) => format!(" at {time}"),
+            _ => String::new(),
+        };
+        match &self.message {
+            Some(message) => message.clone(),
+            None => match facts.get(&self.fact) {
+                Some(value) => format!(
+                    "{} is {}{} ({:?} {})",
+                    self.fact, value, at, self.comparison, self.threshold
+                ),
+                None => format!("{} is unavailable", self.fact),
+            },
+        }
+    }
+}
+
+// Remembers which rules fired last time so a notification is only sent when a rule
+// starts matching, not on every iteration it keeps matching
+#[derive(Debug, Default)]
+pub struct RuleState {
+    active: HashSet<String>,
+}
+
+impl RuleState {
+    pub fn newly_triggered<'a>(&mut self, rules: &'a [Rule], facts: &Facts) -> Vec<&'a Rule> {
+        let mut triggered = Vec::new();
+        for rule in rules {
+            if rule.matches(facts) {
+                if self.active.insert(rule.name.clone()) {
+                    triggered.push(rule);
+                }
+            } else {
+                self.active.remove(&rule.name);
+            }
+        }
+        triggered
+    }
+}
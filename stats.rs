@@ -0,0 +1,425 @@
+use crate::facts::Facts;
+use crate::history::Observation;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeDelta, Timelike};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StatsConfiguration {
+    pub degree_day_base_c: f64,
+    pub trend_window: Duration,
+    pub pressure_drop_mb: f64,
+}
+
+impl Default for StatsConfiguration {
+    fn default() -> Self {
+        Self {
+            degree_day_base_c: 18.0,
+            trend_window: Duration::new(3 * 3600, 0),
+            pressure_drop_mb: 3.0,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Period {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Clone)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    pub min_c: f64,
+    pub max_c: f64,
+    pub mean_c: f64,
+    pub rain_mm: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeriodSummary {
+    pub label: String,
+    pub days: usize,
+    pub min_c: f64,
+    pub max_c: f64,
+    pub mean_c: f64,
+    pub rain_mm: f64,
+    pub heating_degree_days: f64,
+    pub cooling_degree_days: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PressureDrop {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub change_mb: f64,
+}
+
+// What add_facts needs from the days the daemon no longer keeps observations for
+#[derive(Debug, Clone, Default)]
+pub struct Earlier {
+    pub record_high_c: Option<f64>,
+    pub record_low_c: Option<f64>,
+    // The days since the last one with rain, for the dry streak
+    pub dry_days: Vec<DaySummary>,
+}
+
+impl Earlier {
+    // Summarizes and removes the observations from before yesterday that are also older
+    // than the trend window needs. Observations must be in chronological order.
+    pub fn absorb(&mut self, observations: &mut Vec<Observation>, window: Duration) {
+        let Some(latest) = observations.last() else {
+            return;
+        };
+        let Some(reach) = TimeDelta::from_std(window.saturating_add(window / 2))
+            .ok()
+            .and_then(|reach| latest.local_time.checked_sub_signed(reach))
+        else {
+            return;
+        };
+        let Some(yesterday) = latest.local_time.date().pred_opt() else {
+            return;
+        };
+        let keep_from = yesterday.min(reach.date());
+        let split = observations.partition_point(|o| o.local_time.date() < keep_from);
+        for day in daily(&observations[..split]) {
+            self.record_high_c = Some(self.record_high_c.map_or(day.max_c, |high| high.max(day.max_c)));
+            self.record_low_c = Some(self.record_low_c.map_or(day.min_c, |low| low.min(day.min_c)));
+            let follows = self.dry_days.last().is_none_or(|last| last.date.succ_opt() == Some(day.date));
+            if day.rain_mm > 0.0 || !follows {
+                self.dry_days.clear();
+            }
+            if day.rain_mm <= 0.0 {
+                self.dry_days.push(day);
+            }
+        }
+        observations.drain(..split);
+    }
+}
+
+impl DaySummary {
+    // Degree days use the mean of the day's extremes, as is conventional
+    pub fn heating_degree_days(&self, base_c: f64) -> f64 {
+        (base_c - (self.max_c + self.min_c) / 2.0).max(0.0)
+    }
+
+    pub fn cooling_degree_days(&self, base_c: f64) -> f64 {
+        ((self.max_c + self.min_c) / 2.0 - base_c).max(0.0)
+    }
+}
+
+// Observations must belong to a single location
+pub fn daily(observations: &[Observation]) -> Vec<DaySummary> {
+    let mut days: BTreeMap<NaiveDate, Vec<&Observation>> = BTreeMap::new();
+    for observation in observations {
+        days.entry(observation.local_time.date())
+            .or_default()
+            .push(observation);
+    }
+
+    days.into_iter()
+        .map(|(date, observations)| {
+            let temps = observations.iter().map(|o| o.temp_c);
+            // precip_mm is a running amount for the last hour, so summing every
+            // sample would count the same rain several times
+            let mut hourly_rain: BTreeMap<u32, f64> = BTreeMap::new();
+            for observation in &observations {
+                let hour = hourly_rain
+                    .entry(observation.local_time.hour())
+                    .or_insert(0.0);
+                *hour = hour.max(observation.precip_mm);
+            }
+            DaySummary {
+                date,
+                min_c: temps.clone().fold(f64::INFINITY, f64::min),
+                max_c: temps.clone().fold(f64::NEG_INFINITY, f64::max),
+                mean_c: temps.sum::<f64>() / observations.len() as f64,
+                rain_mm: hourly_rain.values().sum(),
+            }
+        })
+        .collect()
+}
+
+pub fn summarize(days: &[DaySummary], period: Period, base_c: f64) -> Vec<PeriodSummary> {
+    let mut periods: BTreeMap<String, Vec<&DaySummary>> = BTreeMap::new();
+    for day in days {
+        let label = match period {
+            Period::Daily => day.date.format("%Y-%m-%d").to_string(),
+            Period::Weekly => {
+                let week = day.date.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Monthly => day.date.format("%Y-%m").to_string(),
+        };
+        periods.entry(label).or_default().push(day);
+    }
+
+    periods
+        .into_iter()
+        .map(|(label, days)| PeriodSummary {
+            label,
+            days: days.len(),
+            min_c: days.iter().map(|d| d.min_c).fold(f64::INFINITY, f64::min),
+            max_c: days.iter().map(|d| d.max_c).fold(f64::NEG_INFINITY, f64::max),
+            mean_c: days.iter().map(|d| d.mean_c).sum::<f64>() / days.len() as f64,
+            rain_mm: days.iter().map(|d| d.rain_mm).sum(),
+            heating_degree_days: days.iter().map(|d| d.heating_degree_days(base_c)).sum(),
+            cooling_degree_days: days.iter().map(|d| d.cooling_degree_days(base_c)).sum(),
+        })
+        .collect()
+}
+
+// Runs of consecutive days without rain, as (first day, length). A missing day breaks a run.
+pub fn dry_streaks(days: &[DaySummary]) -> Vec<(NaiveDate, usize)> {
+    let mut streaks = Vec::new();
+    let mut current: Option<(NaiveDate, NaiveDate, usize)> = None;
+    for day in days {
+        if day.rain_mm > 0.0 {
+            if let Some((start, _, length)) = current.take() {
+                streaks.push((start, length));
+            }
+            continue;
+        }
+        current = match current {
+            Some((start, last, length)) if day.date.pred_opt() == Some(last) => {
+                Some((start, day.date, length + 1))
+            }
+            Some((start, _, length)) => {
+                streaks.push((start, length));
+                Some((day.date, day.date, 1))
+            }
+            None => Some((day.date, day.date, 1)),
+        };
+    }
+    if let Some((start, _, length)) = current {
+        streaks.push((start, length));
+    }
+    streaks
+}
+
+// The change in a quantity between the latest observation and the one closest to
+// `window` before it, if both have it. Observations must be in chronological order.
+pub fn change_over(
+    observations: &[Observation],
+    window: Duration,
+    quantity: impl Fn(&Observation) -> Option<f64>,
+) -> Option<f64> {
+    let latest = observations.last()?;
+    let target = latest.time - window.as_secs() as i64;
+    let earlier = observations.iter().rfind(|o| o.time <= target)?;
+    // Don't compare against a reading from long before the window started
+    if target - earlier.time > window.as_secs() as i64 / 2 {
+        return None;
+    }
+    Some(quantity(latest)? - quantity(earlier)?)
+}
+
+pub fn pressure_drops(
+    observations: &[Observation],
+    window: Duration,
+    threshold_mb: f64,
+) -> Vec<PressureDrop> {
+    let window = window.as_secs() as i64;
+    let mut drops: Vec<PressureDrop> = Vec::new();
+    for (i, observation) in observations.iter().enumerate() {
+        let Some(earlier) = observations[..i]
+            .iter()
+            .rev()
+            .find(|o| observation.time - o.time >= window)
+        else {
+            continue;
+        };
+        if observation.time - earlier.time > window * 3 / 2 {
+            continue;
+        }
+        // Imported readings may have no pressure
+        let (Some(now_mb), Some(earlier_mb)) = (observation.pressure_mb, earlier.pressure_mb) else {
+            continue;
+        };
+        let change_mb = now_mb - earlier_mb;
+        if change_mb > -threshold_mb {
+            continue;
+        }
+        // Merge overlapping detections into a single event
+        match drops.last_mut() {
+            Some(last) if last.to >= earlier.local_time => {
+                last.to = observation.local_time;
+                last.change_mb = last.change_mb.min(change_mb);
+            }
+            _ => drops.push(PressureDrop {
+                from: earlier.local_time,
+                to: observation.local_time,
+                change_mb,
+            }),
+        }
+    }
+    drops
+}
+
+// Facts derived from history for the location the observations belong to
+pub fn add_facts(facts: &mut Facts, earlier: &Earlier, observations: &[Observation], config: &StatsConfiguration) {
+    let mut days = earlier.dry_days.clone();
+    days.extend(daily(observations));
+    if let Some(today) = days.last() {
+        facts.insert_number("today_min_c", today.min_c);
+        facts.insert_number("today_max_c", today.max_c);
+        facts.insert_number("today_mean_c", today.mean_c);
+        facts.insert_number("today_rain_mm", today.rain_mm);
+        facts.insert_number(
+            "today_heating_degree_days",
+            today.heating_degree_days(config.degree_day_base_c),
+        );
+        facts.insert_number(
+            "today_cooling_degree_days",
+            today.cooling_degree_days(config.degree_day_base_c),
+        );
+        let dry_streak = match dry_streaks(&days).last() {
+            Some((start, length)) if *start + chrono::Days::new(*length as u64 - 1) == today.date => *length,
+            _ => 0,
+        };
+        facts.insert_number("dry_streak_days", dry_streak as f64);
+    }
+    if let Some(record) = days.iter().map(|d| d.max_c).chain(earlier.record_high_c).reduce(f64::max) {
+        facts.insert_number("record_high_c", record);
+    }
+    if let Some(record) = days.iter().map(|d| d.min_c).chain(earlier.record_low_c).reduce(f64::min) {
+        facts.insert_number("record_low_c", record);
+    }
+    if let Some(change) = change_over(observations, config.trend_window, |o| o.pressure_mb) {
+        facts.insert_number("pressure_change_mb", change);
+    }
+    if let Some(change) = change_over(observations, config.trend_window, |o| Some(o.temp_c)) {
+        facts.insert_number("temp_change_c", change);
+    }
+}
+
+pub fn print_report(
+    location: &str,
+    observations: &[Observation],
+    period: Period,
+    config: &StatsConfiguration,
+) {
+    let days = daily(observations);
+    let base_c = config.degree_day_base_c;
+
+    println!("{:=^88}", format!(" {location} "));
+    println!(
+        "{:<12}{:>6}{:>10}{:>10}{:>10}{:>12}{:>14}{:>14}",
+        "Period", "Days", "Min °C", "Max °C", "Mean °C", "Rain mm", "Heating DD", "Cooling DD"
+    );
+    for summary in summarize(&days, period, base_c) {
+        println!(
+            "{:<12}{:>6}{:>10.1}{:>10.1}{:>10.1}{:>12.1}{:>14.1}{:>14.1}",
+            summary.label,
+            summary.days,
+            summary.min_c,
+            summary.max_c,
+            summary.mean_c,
+            summary.rain_mm,
+            summary.heating_degree_days,
+            summary.cooling_degree_days,
+        );
+    }
+    println!("Degree days use a base of {base_c}°C\n");
+
+    if let Some(high) = observations
+        .iter()
+        .max_by(|a, b| a.temp_c.total_cmp(&b.temp_c))
+    {
+        println!("Record High: {:.1}°C at {}", high.temp_c, high.local_time);
+    }
+    if let Some(low) = observations
+        .iter()
+        .min_by(|a, b| a.temp_c.total_cmp(&b.temp_c))
+    {
+        println!("Record Low: {:.1}°C at {}", low.temp_c, low.local_time);
+    }
+    if let Some(wettest) = days.iter().max_by(|a, b| a.rain_mm.total_cmp(&b.rain_mm)) {
+        println!("Wettest Day: {:.1} mm on {}", wettest.rain_mm, wettest.date);
+    }
+    if let Some((start, length)) = dry_streaks(&days).into_iter().max_by_key(|s| s.1) {
+        println!("Longest Dry Streak: {length} day(s) starting {start}");
+    }
+
+    let drops = pressure_drops(
+        observations,
+        config.trend_window,
+        config.pressure_drop_mb,
+    );
+    if drops.is_empty() {
+        println!(
+            "No pressure drops of more than {} mb within {} minutes",
+            config.pressure_drop_mb,
+            config.trend_window.as_secs() / 60
+        );
+    }
+    for drop in drops {
+        println!(
+            "Pressure Drop: {:+.1} mb between {} and {}",
+            drop.change_mb, drop.from, drop.to
+        );
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ten days of hourly readings, raining on the third and seventh
+    fn observations() -> Vec<Observation> {
+        let start = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        (0..240)
+            .map(|hour| {
+                let local_time = start + TimeDelta::hours(hour);
+                let day = hour / 24;
+                Observation {
+                    provider: String::from("test"),
+                    location: String::from("here"),
+                    time: local_time.and_utc().timestamp(),
+                    local_time,
+                    temp_c: (hour % 24) as f64 - day as f64,
+                    feelslike_c: 0.0,
+                    humidity: Some(50.0),
+                    pressure_mb: Some(1010.0 - (hour % 7) as f64),
+                    precip_mm: if day == 2 || day == 6 { 0.5 } else { 0.0 },
+                    wind_kph: Some(5.0),
+                    condition: String::from("Clear"),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn absorbing_old_observations_keeps_the_facts() {
+        let config = StatsConfiguration::default();
+        let all = observations();
+        let mut expected = Facts::default();
+        add_facts(&mut expected, &Earlier::default(), &all, &config);
+
+        let mut earlier = Earlier::default();
+        let mut kept = all.clone();
+        earlier.absorb(&mut kept, config.trend_window);
+        assert_eq!(kept.len(), 48);
+        assert_eq!(earlier.dry_days.len(), 1);
+        let mut facts = Facts::default();
+        add_facts(&mut facts, &earlier, &kept, &config);
+
+        for name in [
+            "today_min_c",
+            "today_max_c",
+            "dry_streak_days",
+            "record_high_c",
+            "record_low_c",
+            "pressure_change_mb",
+            "temp_change_c",
+        ] {
+            assert_eq!(facts.number(name), expected.number(name), "{name}");
+        }
+        assert_eq!(facts.number("dry_streak_days"), Some(3.0));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::facts::{Fact, Facts};
+
+// Fills in {name} placeholders with facts. A number of decimal places can be given
+// with {name:.1}, and {{ / }} produce literal braces. Unknown names render as "?".
+pub fn render(template: &str, facts: &Facts) -> String {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+                output.push_str(&substitute(&placeholder, facts));
+            }
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+fn substitute(placeholder: &str, facts: &Facts) -> String {
+    let (name, precision) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name.trim(), spec.trim().strip_prefix('.').and_then(|p| p.parse().ok())),
+        None => (placeholder.trim(), None),
+    };
+    match (facts.get(name), precision) {
+        (Some(Fact::Number(value)), Some(precision)) => format!("{value:.precision$}"),
+        (Some(fact), _) => fact.to_string(),
+        (None, _) => String::from("?"),
+    }
+}
@@ -0,0 +1,447 @@
+use crate::derived;
+use crate::facts::Facts;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const UNITS_FILE: &str = "units.ron";
+
+// Every value is kept in one canonical unit, the ones WeatherAPI's metric fields use
+// (°C, km/h, hPa, mm, km and m), and only converted when shown. That way a provider
+// sending a single system works just as well.
+pub trait Unit: Copy {
+    fn symbol(self) -> &'static str;
+    fn in_unit(self, value: f64) -> f64;
+    fn to_canonical(self, value: f64) -> f64;
+    fn decimals(self) -> i32;
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+    // Celsius and millibars, but miles and miles per hour
+    Uk,
+    Si,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Temperature {
+    #[value(alias = "c")]
+    Celsius,
+    #[value(alias = "f")]
+    Fahrenheit,
+    #[value(alias = "k")]
+    Kelvin,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    #[value(aliases = ["km/h", "kph"])]
+    Kph,
+    Mph,
+    #[value(aliases = ["m/s", "ms"])]
+    MetresPerSecond,
+    #[value(aliases = ["kn", "kt"])]
+    Knots,
+    #[value(alias = "bft")]
+    Beaufort,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Pressure {
+    #[value(aliases = ["hpa", "mb"])]
+    Hectopascals,
+    #[value(alias = "inhg")]
+    InchesOfMercury,
+    #[value(alias = "mmhg")]
+    MillimetresOfMercury,
+    #[value(alias = "kpa")]
+    Kilopascals,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Precipitation {
+    #[value(alias = "mm")]
+    Millimetres,
+    #[value(alias = "in")]
+    Inches,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Distance {
+    #[value(alias = "km")]
+    Kilometres,
+    #[value(alias = "mi")]
+    Miles,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Height {
+    #[value(alias = "m")]
+    Metres,
+    #[value(alias = "ft")]
+    Feet,
+}
+
+impl Unit for Temperature {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+            Self::Kelvin => "K",
+        }
+    }
+
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Celsius => value,
+            Self::Fahrenheit => value * 9.0 / 5.0 + 32.0,
+            Self::Kelvin => value + 273.15,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Celsius => value,
+            Self::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            Self::Kelvin => value - 273.15,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        1
+    }
+}
+
+impl Unit for Speed {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Kph => "km/h",
+            Self::Mph => "mph",
+            Self::MetresPerSecond => "m/s",
+            Self::Knots => "kn",
+            Self::Beaufort => "Bft",
+        }
+    }
+
+    // Beaufort forces go by the usual table, converting back gives the empirical
+    // v = 0.836 B^1.5 m/s
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Kph => value,
+            Self::Mph => value / 1.609344,
+            Self::MetresPerSecond => value / 3.6,
+            Self::Knots => value / 1.852,
+            Self::Beaufort => derived::beaufort(value) as f64,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Kph => value,
+            Self::Mph => value * 1.609344,
+            Self::MetresPerSecond => value * 3.6,
+            Self::Knots => value * 1.852,
+            Self::Beaufort => 0.836 * value.max(0.0).powf(1.5) * 3.6,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        match self {
+            Self::Beaufort => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl Unit for Pressure {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Hectopascals => "hPa",
+            Self::InchesOfMercury => "inHg",
+            Self::MillimetresOfMercury => "mmHg",
+            Self::Kilopascals => "kPa",
+        }
+    }
+
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Hectopascals => value,
+            Self::InchesOfMercury => value * 0.02953,
+            Self::MillimetresOfMercury => value * 0.750062,
+            Self::Kilopascals => value / 10.0,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Hectopascals => value,
+            Self::InchesOfMercury => value / 0.02953,
+            Self::MillimetresOfMercury => value / 0.750062,
+            Self::Kilopascals => value * 10.0,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        match self {
+            Self::Hectopascals | Self::MillimetresOfMercury => 0,
+            Self::InchesOfMercury => 2,
+            Self::Kilopascals => 1,
+        }
+    }
+}
+
+impl Unit for Precipitation {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Millimetres => "mm",
+            Self::Inches => "in",
+        }
+    }
+
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Millimetres => value,
+            Self::Inches => value / 25.4,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Millimetres => value,
+            Self::Inches => value * 25.4,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        match self {
+            Self::Millimetres => 1,
+            Self::Inches => 2,
+        }
+    }
+}
+
+impl Unit for Distance {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Kilometres => "km",
+            Self::Miles => "mi",
+        }
+    }
+
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Kilometres => value,
+            Self::Miles => value / 1.609344,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Kilometres => value,
+            Self::Miles => value * 1.609344,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        1
+    }
+}
+
+impl Unit for Height {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Metres => "m",
+            Self::Feet => "ft",
+        }
+    }
+
+    fn in_unit(self, value: f64) -> f64 {
+        match self {
+            Self::Metres => value,
+            Self::Feet => value * 3.28084,
+        }
+    }
+
+    fn to_canonical(self, value: f64) -> f64 {
+        match self {
+            Self::Metres => value,
+            Self::Feet => value / 3.28084,
+        }
+    }
+
+    fn decimals(self) -> i32 {
+        1
+    }
+}
+
+// A value in `unit` rounded for reading, e.g. "12.5°C" or "29.88 inHg"
+pub fn with_unit<U: Unit>(unit: U, value: Option<f64>) -> String {
+    let symbol = unit.symbol();
+    let separator = if symbol.starts_with('°') { "" } else { " " };
+    match value {
+        Some(value) => format!("{}{separator}{symbol}", round(unit, unit.in_unit(value))),
+        None => format!("-{separator}{symbol}"),
+    }
+}
+
+fn round<U: Unit>(unit: U, value: f64) -> f64 {
+    let scale = 10f64.powi(unit.decimals());
+    // Adding zero turns -0 into 0
+    (value * scale).round() / scale + 0.0
+}
+
+// One --unit override, e.g. speed=knots
+#[derive(Clone, Copy, Debug)]
+pub enum UnitChoice {
+    Temperature(Temperature),
+    Speed(Speed),
+    Pressure(Pressure),
+    Precipitation(Precipitation),
+    Distance(Distance),
+    Height(Height),
+}
+
+pub fn parse_choice(s: &str) -> Result<UnitChoice, String> {
+    let Some((quantity, unit)) = s.split_once('=') else {
+        return Err(format!("expected QUANTITY=UNIT, found {s}"));
+    };
+    let choice = match quantity.trim().to_lowercase().as_str() {
+        "temperature" | "temp" => Temperature::from_str(unit.trim(), true).map(UnitChoice::Temperature),
+        "speed" | "wind" => Speed::from_str(unit.trim(), true).map(UnitChoice::Speed),
+        "pressure" => Pressure::from_str(unit.trim(), true).map(UnitChoice::Pressure),
+        "precipitation" | "precip" => Precipitation::from_str(unit.trim(), true).map(UnitChoice::Precipitation),
+        "distance" | "visibility" => Distance::from_str(unit.trim(), true).map(UnitChoice::Distance),
+        "height" => Height::from_str(unit.trim(), true).map(UnitChoice::Height),
+        _ => {
+            return Err(format!(
+                "unknown quantity {quantity}, expected temperature, speed, pressure, precipitation, distance or height"
+            ))
+        }
+    };
+    choice.map_err(|e| format!("unknown {quantity} unit {unit}: {e}"))
+}
+
+// What units.ron holds, e.g. (system: Uk, speed: Some(Knots)). Quantities left out
+// follow the system.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DisplayUnits {
+    #[serde(default)]
+    pub system: UnitSystem,
+    #[serde(default)]
+    pub temperature: Option<Temperature>,
+    #[serde(default)]
+    pub speed: Option<Speed>,
+    #[serde(default)]
+    pub pressure: Option<Pressure>,
+    #[serde(default)]
+    pub precipitation: Option<Precipitation>,
+    #[serde(default)]
+    pub distance: Option<Distance>,
+    #[serde(default)]
+    pub height: Option<Height>,
+}
+
+impl DisplayUnits {
+    pub fn temperature(&self) -> Temperature {
+        self.temperature.unwrap_or(match self.system {
+            UnitSystem::Metric | UnitSystem::Uk => Temperature::Celsius,
+            UnitSystem::Imperial => Temperature::Fahrenheit,
+            UnitSystem::Si => Temperature::Kelvin,
+        })
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed.unwrap_or(match self.system {
+            UnitSystem::Metric => Speed::Kph,
+            UnitSystem::Imperial | UnitSystem::Uk => Speed::Mph,
+            UnitSystem::Si => Speed::MetresPerSecond,
+        })
+    }
+
+    pub fn pressure(&self) -> Pressure {
+        self.pressure.unwrap_or(match self.system {
+            UnitSystem::Imperial => Pressure::InchesOfMercury,
+            _ => Pressure::Hectopascals,
+        })
+    }
+
+    pub fn precipitation(&self) -> Precipitation {
+        self.precipitation.unwrap_or(match self.system {
+            UnitSystem::Imperial => Precipitation::Inches,
+            _ => Precipitation::Millimetres,
+        })
+    }
+
+    pub fn distance(&self) -> Distance {
+        self.distance.unwrap_or(match self.system {
+            UnitSystem::Imperial | UnitSystem::Uk => Distance::Miles,
+            _ => Distance::Kilometres,
+        })
+    }
+
+    pub fn height(&self) -> Height {
+        self.height.unwrap_or(match self.system {
+            UnitSystem::Imperial => Height::Feet,
+            _ => Height::Metres,
+        })
+    }
+
+    pub fn choose(&mut self, choice: UnitChoice) {
+        match choice {
+            UnitChoice::Temperature(unit) => self.temperature = Some(unit),
+            UnitChoice::Speed(unit) => self.speed = Some(unit),
+            UnitChoice::Pressure(unit) => self.pressure = Some(unit),
+            UnitChoice::Precipitation(unit) => self.precipitation = Some(unit),
+            UnitChoice::Distance(unit) => self.distance = Some(unit),
+            UnitChoice::Height(unit) => self.height = Some(unit),
+        }
+    }
+
+    // Unitless copies of the canonical facts in these units, with the symbols as text,
+    // so templates can use "{temp:.0}{temp_unit}" whatever the units are
+    pub fn add_facts(&self, facts: &mut Facts) {
+        fn convert<U: Unit>(facts: &mut Facts, unit: U, names: &[(&str, &str)], symbol: &str) {
+            for (name, canonical) in names {
+                if let Some(value) = facts.number(canonical) {
+                    facts.insert_number(name, unit.in_unit(value));
+                }
+            }
+            facts.insert_text(symbol, unit.symbol());
+        }
+        let temperatures = [
+            ("temp", "temp_c"),
+            ("feelslike", "feelslike_c"),
+            ("dewpoint", "dewpoint_c"),
+            ("windchill", "windchill_c"),
+            ("heatindex", "heatindex_c"),
+            ("wetbulb", "wetbulb_c"),
+            ("apparent_temp", "apparent_temp_c"),
+            ("forecast_maxtemp", "forecast_maxtemp_c"),
+            ("forecast_mintemp", "forecast_mintemp_c"),
+        ];
+        convert(facts, self.temperature(), &temperatures, "temp_unit");
+        convert(facts, self.speed(), &[("wind", "wind_kph"), ("gust", "gust_kph")], "speed_unit");
+        convert(facts, self.pressure(), &[("pressure", "pressure_mb")], "pressure_unit");
+        convert(facts, self.precipitation(), &[("precip", "precip_mm")], "precip_unit");
+        convert(facts, self.distance(), &[("vis", "vis_km")], "distance_unit");
+        for hour in facts.hours.iter_mut() {
+            self.add_facts(hour);
+        }
+    }
+}
+
+pub fn load(working_directory: &Path) -> DisplayUnits {
+    let Ok(data) = std::fs::read_to_string(working_directory.join(UNITS_FILE)) else {
+        return DisplayUnits::default();
+    };
+    match ron::from_str(&data) {
+        Ok(units) => units,
+        Err(e) => {
+            eprintln!("Failed to parse {UNITS_FILE}: {e:?}");
+            DisplayUnits::default()
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta, Timelike, Weekday};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr, time::Duration};
 
@@ -10,15 +11,225 @@ impl From<Duration> for DurationWrapper {
     }
 }
 
-impl Into<Duration> for DurationWrapper {
-    fn into(self) -> Duration {
-        self.0
+impl From<DurationWrapper> for Duration {
+    fn from(value: DurationWrapper) -> Self {
+        value.0
     }
 }
 
-impl From<String> for DurationWrapper {
-    fn from(value: String) -> Self {
-        Self::from_str(value.as_ref()).unwrap_or(Self(Duration::new(600, 0)))
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+// Every unit a duration can be written in, largest first, with its short name first
+const UNITS: [(u128, &[&str]); 8] = [
+    (604_800 * NANOS_PER_SEC, &["w", "week", "weeks"]),
+    (86_400 * NANOS_PER_SEC, &["d", "day", "days"]),
+    (3_600 * NANOS_PER_SEC, &["h", "hr", "hrs", "hour", "hours"]),
+    (60 * NANOS_PER_SEC, &["m", "min", "mins", "minute", "minutes"]),
+    (NANOS_PER_SEC, &["s", "sec", "secs", "second", "seconds"]),
+    (1_000_000, &["ms", "msec", "msecs", "milli", "millis", "millisecond", "milliseconds"]),
+    (1_000, &["us", "µs", "usec", "usecs", "micro", "micros", "microsecond", "microseconds"]),
+    (1, &["ns", "nsec", "nsecs", "nano", "nanos", "nanosecond", "nanoseconds"]),
+];
+
+// Positions count characters from 1
+#[derive(Debug, Clone, PartialEq)]
+pub enum DurationParseError {
+    Empty,
+    ExpectedNumber(usize),
+    MissingUnit(usize),
+    UnknownUnit(usize, String),
+    TooLong(usize),
+    // An ISO 8601 duration that is written wrong or can't be turned into a fixed length
+    Iso(usize, &'static str),
+}
+
+impl Display for DurationParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty duration"),
+            Self::ExpectedNumber(position) => write!(f, "expected a number at position {position}"),
+            Self::MissingUnit(position) => write!(f, "missing unit at position {position}, like d, h, m, s or ms"),
+            Self::UnknownUnit(position, unit) => write!(f, "unknown unit {unit:?} at position {position}"),
+            Self::TooLong(position) => write!(f, "duration too long at position {position}"),
+            Self::Iso(position, reason) => write!(f, "{reason} at position {position}"),
+        }
+    }
+}
+
+impl std::error::Error for DurationParseError {}
+
+struct Scanner {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Scanner {
+    fn position(&self) -> usize {
+        self.at + 1
+    }
+
+    fn done(&self) -> bool {
+        self.at >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.at;
+        while self.peek().is_some_and(&f) {
+            self.at += 1;
+        }
+        self.chars[start..self.at].iter().collect()
+    }
+
+    // Skips `word` if it comes next as a whole word
+    fn keyword(&mut self, word: &str) -> bool {
+        let start = self.at;
+        match self.take_while(char::is_alphabetic).eq_ignore_ascii_case(word) {
+            true => true,
+            false => {
+                self.at = start;
+                false
+            }
+        }
+    }
+
+    // A whole number with an optional fraction, as the number and the fraction's digits
+    fn number(&mut self) -> Result<(u128, String), DurationParseError> {
+        let position = self.position();
+        let whole = self.take_while(|c| c.is_ascii_digit());
+        let mut fraction = String::new();
+        if self.peek() == Some('.') {
+            self.at += 1;
+            fraction = self.take_while(|c| c.is_ascii_digit());
+        }
+        if whole.is_empty() && fraction.is_empty() {
+            self.at = position - 1;
+            return Err(DurationParseError::ExpectedNumber(position));
+        }
+        let whole = match whole.is_empty() {
+            true => 0,
+            false => whole.parse().map_err(|_| DurationParseError::TooLong(position))?,
+        };
+        Ok((whole, fraction))
+    }
+}
+
+// `whole`.`fraction` of a unit `nanos` long in nanoseconds, dropping anything under one
+fn scale(whole: u128, fraction: &str, nanos: u128, position: usize) -> Result<u128, DurationParseError> {
+    // 18 digits are well past a nanosecond of any unit and can't overflow
+    let digits = &fraction[..fraction.len().min(18)];
+    let part = match digits.is_empty() {
+        true => 0,
+        false => nanos * digits.parse::<u128>().unwrap_or(0) / 10u128.pow(digits.len() as u32),
+    };
+    whole
+        .checked_mul(nanos)
+        .and_then(|total| total.checked_add(part))
+        .ok_or(DurationParseError::TooLong(position))
+}
+
+// Adds the component at `position` to the total so far, which has to stay within a Duration
+fn add(total: u128, part: u128, position: usize) -> Result<u128, DurationParseError> {
+    total
+        .checked_add(part)
+        .filter(|total| *total <= Duration::MAX.as_nanos())
+        .ok_or(DurationParseError::TooLong(position))
+}
+
+fn to_duration(nanos: u128) -> Duration {
+    Duration::new((nanos / NANOS_PER_SEC) as u64, (nanos % NANOS_PER_SEC) as u32)
+}
+
+impl DurationWrapper {
+    // "1h30m", "1h 30m", "1 hour and 30 minutes", "1.5h" or "90 mins"
+    fn parse_units(scanner: &mut Scanner) -> Result<u128, DurationParseError> {
+        let mut total: u128 = 0;
+        let mut terms = 0;
+        loop {
+            scanner.take_while(|c| c.is_whitespace() || c == ',');
+            if scanner.done() {
+                break;
+            }
+            if terms > 0 && scanner.keyword("and") {
+                scanner.take_while(char::is_whitespace);
+            }
+            let position = scanner.position();
+            let (whole, fraction) = scanner.number()?;
+            scanner.take_while(char::is_whitespace);
+            let unit_position = scanner.position();
+            let unit = scanner.take_while(char::is_alphabetic).to_lowercase();
+            if unit.is_empty() {
+                return Err(DurationParseError::MissingUnit(unit_position));
+            }
+            let Some((nanos, _)) = UNITS.iter().find(|(_, names)| names.contains(&unit.as_str())) else {
+                return Err(DurationParseError::UnknownUnit(unit_position, unit));
+            };
+            total = add(total, scale(whole, &fraction, *nanos, position)?, position)?;
+            terms += 1;
+        }
+        match terms {
+            0 => Err(DurationParseError::Empty),
+            _ => Ok(total),
+        }
+    }
+
+    // ISO 8601 durations without years and months, like "PT1H30M", "P1DT12H" or "P2W"
+    fn parse_iso(scanner: &mut Scanner) -> Result<u128, DurationParseError> {
+        scanner.at += 1;
+        let mut total: u128 = 0;
+        let mut time = false;
+        let mut terms = 0;
+        // Designators have to come in order, and only the last may have a fraction
+        let mut last = 0;
+        let mut fractional = false;
+        while !scanner.done() {
+            let position = scanner.position();
+            if scanner.peek().is_some_and(|c| c.eq_ignore_ascii_case(&'T')) {
+                if time {
+                    return Err(DurationParseError::Iso(position, "T given twice"));
+                }
+                scanner.at += 1;
+                time = true;
+                terms = 0;
+                if scanner.done() {
+                    return Err(DurationParseError::ExpectedNumber(scanner.position()));
+                }
+                continue;
+            }
+            if fractional {
+                return Err(DurationParseError::Iso(position, "only the last number can have a fraction"));
+            }
+            let (whole, fraction) = scanner.number()?;
+            let designator_position = scanner.position();
+            let Some(designator) = scanner.peek() else {
+                return Err(DurationParseError::MissingUnit(designator_position));
+            };
+            scanner.at += 1;
+            let (rank, nanos) = match (time, designator.to_ascii_uppercase()) {
+                (false, 'Y') => return Err(DurationParseError::Iso(designator_position, "years have no fixed length")),
+                (false, 'M') => return Err(DurationParseError::Iso(designator_position, "months have no fixed length")),
+                (false, 'W') => (1, UNITS[0].0),
+                (false, 'D') => (2, UNITS[1].0),
+                (true, 'H') => (3, UNITS[2].0),
+                (true, 'M') => (4, UNITS[3].0),
+                (true, 'S') => (5, UNITS[4].0),
+                _ => return Err(DurationParseError::UnknownUnit(designator_position, designator.to_string())),
+            };
+            if rank <= last {
+                return Err(DurationParseError::Iso(designator_position, "designator out of order"));
+            }
+            last = rank;
+            fractional = !fraction.is_empty();
+            total = add(total, scale(whole, &fraction, nanos, position)?, position)?;
+            terms += 1;
+        }
+        match terms {
+            0 => Err(DurationParseError::ExpectedNumber(scanner.position())),
+            _ => Ok(total),
+        }
     }
 }
 
@@ -26,44 +237,637 @@ impl FromStr for DurationWrapper {
     type Err = DurationParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.to_lowercase().replace(" ", "");
-        let pattern = &['s', 'm', 'h'];
-        let matches = s.split(pattern).zip(s.matches(pattern));
+        let mut scanner = Scanner {
+            chars: s.trim_end().chars().collect(),
+            at: 0,
+        };
+        scanner.take_while(char::is_whitespace);
+        let nanos = match scanner.peek() {
+            None => return Err(DurationParseError::Empty),
+            Some('P' | 'p') => Self::parse_iso(&mut scanner)?,
+            Some(_) => Self::parse_units(&mut scanner)?,
+        };
+        Ok(Self(to_duration(nanos)))
+    }
+}
 
-        let mut dur = Duration::ZERO;
-        let mut iterations = 0;
-        for (text, unit) in matches {
-            iterations += 1;
-            let val: u64 = text
-                .parse()
-                .map_err(|_| DurationParseError::InvalidNumber)?;
-            match unit {
-                "s" => dur += Duration::new(val, 0),
-                "m" => dur += Duration::new(val * 60, 0),
-                "h" => dur += Duration::new(val * 3600, 0),
-                _ => break,
+// Largest units first and days at most, like "1d2h", "1m30s" or "250ms", which parses back
+// to the same duration
+impl Display for DurationWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rest = self.0.as_nanos();
+        if rest == 0 {
+            return write!(f, "0s");
+        }
+        for (nanos, names) in &UNITS[1..] {
+            if rest >= *nanos {
+                write!(f, "{}{}", rest / nanos, names[0])?;
+                rest %= nanos;
             }
         }
+        Ok(())
+    }
+}
+
+// When the daemon does something: an interval after the last time ("10m" or "every 1 hour"), times of day
+// ("07:00,12:00,17:30"), a cron expression ("*/10 9-17 * * Mon-Fri"), or several of
+// these separated by ';'. Intervals and times can be limited to some days by putting
+// them first, as in "Mon-Fri 07:30" or "Sat,Sun 1h".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Schedule {
+    #[default]
+    Never,
+    Every { interval: Duration, days: Weekdays },
+    At { times: Vec<NaiveTime>, days: Weekdays },
+    Cron(Cron),
+    Any(Vec<Schedule>),
+}
+
+// Bit 0 is Monday
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weekdays(u8);
 
-        if iterations == 0 {
-            Err(DurationParseError::DurationNotFound)
-        } else {
-            Ok(Self(dur))
+// The usual five fields, minute hour day-of-month month day-of-week, each a bit set
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    text: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    // Bit 0 is Sunday, as cron counts
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+#[derive(Debug)]
+pub enum ScheduleParseError {
+    Empty,
+    Interval(String, Option<DurationParseError>),
+    Time(String),
+    Weekdays(String),
+    Cron(String),
+}
+
+impl Display for ScheduleParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty schedule"),
+            Self::Interval(text, None) => write!(f, "{text:?} is not an interval like 10m or 1h30m"),
+            Self::Interval(text, Some(e)) => write!(f, "{text:?} is not an interval like 10m or 1h30m, {e}"),
+            Self::Time(text) => write!(f, "{text:?} is not a time of day like 07:30"),
+            Self::Weekdays(text) => write!(f, "{text:?} is not a list of days like Mon-Fri or Sat,Sun"),
+            Self::Cron(text) => write!(f, "{text:?} is not a valid cron field"),
         }
     }
 }
 
-impl Display for DurationWrapper {
+impl std::error::Error for ScheduleParseError {}
+
+impl Weekdays {
+    pub const ALL: Self = Self(0x7f);
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & (1 << day.num_days_from_monday()) != 0
+    }
+}
+
+impl FromStr for Weekdays {
+    type Err = ScheduleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ScheduleParseError::Weekdays(s.to_string());
+        let day = |text: &str| text.parse::<Weekday>().map_err(|_| error());
+        let mut mask = 0;
+        for item in s.split(',') {
+            match item.to_lowercase().as_str() {
+                "weekdays" => mask |= 0x1f,
+                "weekends" => mask |= 0x60,
+                _ => {
+                    let (first, last) = match item.split_once('-') {
+                        Some((first, last)) => (day(first)?, day(last)?),
+                        None => (day(item)?, day(item)?),
+                    };
+                    // Ranges may wrap round the end of the week, like Sat-Mon
+                    let mut current = first;
+                    loop {
+                        mask |= 1 << current.num_days_from_monday();
+                        if current == last {
+                            break;
+                        }
+                        current = current.succ();
+                    }
+                }
+            }
+        }
+        Ok(Self(mask))
+    }
+}
+
+impl Display for Weekdays {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days: Vec<Weekday> = (0..7)
+            .filter(|i| self.0 & (1 << i) != 0)
+            .filter_map(|i| Weekday::try_from(i as u8).ok())
+            .collect();
+        // Consecutive days are written as ranges
+        let mut runs: Vec<(Weekday, Weekday)> = Vec::new();
+        for day in days {
+            match runs.last_mut() {
+                Some((_, last)) if last.succ() == day => *last = day,
+                _ => runs.push((day, day)),
+            }
+        }
+        // A run ending on Sunday carries on into one starting on Monday, like Fri-Mon
+        if runs.len() > 1 && runs[0].0 == Weekday::Mon && runs[runs.len() - 1].1 == Weekday::Sun {
+            let (_, last) = runs.remove(0);
+            runs.last_mut().unwrap().1 = last;
+        }
+        let runs: Vec<String> = runs
+            .iter()
+            .map(|(first, last)| match first == last {
+                true => first.to_string(),
+                false => format!("{first}-{last}"),
+            })
+            .collect();
+        write!(f, "{}", runs.join(","))
+    }
+}
+
+impl Cron {
+    fn field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, ScheduleParseError> {
+        let error = || ScheduleParseError::Cron(text.to_string());
+        let value = |text: &str| -> Result<u32, ScheduleParseError> {
+            let value = match names.iter().position(|name| name.eq_ignore_ascii_case(text)) {
+                Some(position) => position as u32 + min,
+                None => text.parse().map_err(|_| error())?,
+            };
+            (min..=max).contains(&value).then_some(value).ok_or_else(error)
+        };
+        let mut bits = 0;
+        for item in text.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| error())?),
+                None => (item, 1),
+            };
+            if step == 0 {
+                return Err(error());
+            }
+            let (first, last) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((first, last)) => (value(first)?, value(last)?),
+                // A single value with a step runs to the end, like 5/15
+                None if item.contains('/') => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            };
+            for value in (first..=last).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(bits)
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        // Cron runs on either when both are restricted
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    fn next(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.date_naive();
+        // Long enough for anything but the 29th of February to come round
+        for offset in 0..4 * 366 {
+            let date = start + Days::new(offset);
+            if !self.matches_day(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                for minute in (0..60).filter(|minute| self.minutes & (1 << minute) != 0) {
+                    let time = date
+                        .and_hms_opt(hour, minute, 0)?
+                        .and_local_timezone(Local)
+                        .earliest();
+                    if let Some(time) = time.filter(|time| *time > after) {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = ScheduleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(ScheduleParseError::Cron(s.to_string()));
+        };
+        const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+        const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+        let mut weekday_bits = Self::field(weekdays, 0, 7, &WEEKDAYS)?;
+        // 7 is Sunday too
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+        Ok(Self {
+            text: fields.join(" "),
+            minutes: Self::field(minutes, 0, 59, &[])?,
+            hours: Self::field(hours, 0, 23, &[])?,
+            days: Self::field(days, 1, 31, &[])?,
+            months: Self::field(months, 1, 12, &MONTHS)?,
+            weekdays: weekday_bits,
+            // Like *, */1 and 1-31 leave the field unrestricted
+            any_day: Self::field(days, 1, 31, &[])? == Self::field("*", 1, 31, &[])?,
+            any_weekday: weekday_bits & 0x7f == 0x7f,
+        })
+    }
+}
+
+impl Schedule {
+    // The first time strictly after `after`, None if there isn't one
+    pub fn next(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Self::Never => None,
+            Self::Every { interval, days } => {
+                let next = after.checked_add_signed(TimeDelta::from_std(*interval).ok()?)?;
+                if days.contains(next.weekday()) {
+                    return Some(next);
+                }
+                // Otherwise the start of the next day it runs on
+                (1..=7)
+                    .filter_map(|offset| next.date_naive().checked_add_days(Days::new(offset)))
+                    .find(|date| days.contains(date.weekday()))?
+                    .and_time(NaiveTime::MIN)
+                    .and_local_timezone(Local)
+                    .earliest()
+            }
+            Self::At { times, days } => {
+                let mut times = times.clone();
+                times.sort();
+                (0..=7)
+                    .filter_map(|offset| after.date_naive().checked_add_days(Days::new(offset)))
+                    .filter(|date| days.contains(date.weekday()))
+                    .flat_map(|date| times.iter().map(move |time| date.and_time(*time)))
+                    .filter_map(|time| time.and_local_timezone(Local).earliest())
+                    .find(|time| *time > after)
+            }
+            Self::Cron(cron) => cron.next(after),
+            Self::Any(schedules) => schedules.iter().filter_map(|schedule| schedule.next(after)).min(),
+        }
+    }
+
+    // Whether a time has come round since `since`
+    pub fn due(&self, since: DateTime<Local>, now: DateTime<Local>) -> bool {
+        self.next(since).is_some_and(|next| next <= now)
+    }
+
+    fn parse_part(text: &str) -> Result<Self, ScheduleParseError> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let Some(first) = fields.first() else {
+            return Err(ScheduleParseError::Empty);
+        };
+        if text == "never" {
+            return Ok(Self::Never);
+        }
+        // Intervals can be written in words, as in "every 1 hour", so they go first
+        if let Ok(schedule) = Self::parse_body(text, Weekdays::ALL) {
+            return Ok(schedule);
+        }
+        if fields.len() == 5 && first.parse::<Weekdays>().is_err() {
+            return Ok(Self::Cron(text.parse()?));
+        }
+        if fields.len() == 1 || first.eq_ignore_ascii_case("every") {
+            return Self::parse_body(text, Weekdays::ALL);
+        }
+        Self::parse_body(text[first.len()..].trim(), first.parse()?)
+    }
+
+    // Times of day or an interval, on `days`
+    fn parse_body(text: &str, days: Weekdays) -> Result<Self, ScheduleParseError> {
+        if text.contains(':') {
+            let times = text
+                .split(',')
+                .map(str::trim)
+                .map(|time| {
+                    NaiveTime::parse_from_str(time, "%H:%M")
+                        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+                        .map_err(|_| ScheduleParseError::Time(time.to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok(Self::At { times, days });
+        }
+        let interval = match text.get(..6) {
+            Some(every) if every.eq_ignore_ascii_case("every ") => &text[6..],
+            _ => text,
+        };
+        let interval: Duration = DurationWrapper::from_str(interval)
+            .map_err(|e| ScheduleParseError::Interval(text.to_string(), Some(e)))?
+            .into();
+        if interval.is_zero() {
+            return Err(ScheduleParseError::Interval(text.to_string(), None));
+        }
+        Ok(Self::Every { interval, days })
+    }
+}
+
+impl From<Duration> for Schedule {
+    fn from(interval: Duration) -> Self {
+        Self::Every {
+            interval,
+            days: Weekdays::ALL,
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ScheduleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s
+            .split(';')
+            .map(|part| Self::parse_part(part.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parts.len() {
+            1 => Ok(parts.remove(0)),
+            _ => Ok(Self::Any(parts)),
+        }
+    }
+}
+
+impl Display for Schedule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut secs = self.0.as_secs();
-        let mins = secs / 60;
-        secs = secs % 60;
+        let days = |days: &Weekdays| match *days == Weekdays::ALL {
+            true => String::new(),
+            false => format!("{days} "),
+        };
+        match self {
+            Self::Never => write!(f, "never"),
+            Self::Every { interval, days: on } => write!(f, "{}{}", days(on), DurationWrapper(*interval)),
+            Self::At { times, days: on } => {
+                let times: Vec<String> = times
+                    .iter()
+                    .map(|time| match time.second() {
+                        0 => time.format("%H:%M").to_string(),
+                        _ => time.format("%H:%M:%S").to_string(),
+                    })
+                    .collect();
+                write!(f, "{}{}", days(on), times.join(","))
+            }
+            Self::Cron(cron) => write!(f, "{}", cron.text),
+            Self::Any(schedules) => {
+                let parts: Vec<String> = schedules.iter().map(Schedule::to_string).collect();
+                write!(f, "{}", parts.join("; "))
+            }
+        }
+    }
+}
 
-        write!(f, "{mins}m{secs}s")
+// Written as text, but plain durations and lists of times from older config files still load
+impl Serialize for Schedule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
-pub enum DurationParseError {
-    InvalidNumber,
-    DurationNotFound,
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            Text(String),
+            Interval(Duration),
+            Times(Vec<NaiveTime>),
+        }
+        match Written::deserialize(deserializer)? {
+            Written::Text(text) => text.parse().map_err(serde::de::Error::custom),
+            Written::Interval(interval) => Ok(interval.into()),
+            Written::Times(times) if times.is_empty() => Ok(Self::Never),
+            Written::Times(times) => Ok(Self::At {
+                times,
+                days: Weekdays::ALL,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn parse(text: &str) -> Result<Duration, DurationParseError> {
+        text.parse::<DurationWrapper>().map(Duration::from)
+    }
+
+    #[test]
+    fn parses_every_form() {
+        let cases = [
+            ("10m30s", 630_000),
+            ("125m", 7_500_000),
+            ("1d", 86_400_000),
+            ("1.5h", 5_400_000),
+            ("500ms", 500),
+            ("1 hour 30 minutes", 5_400_000),
+            ("1 hour and 30 minutes", 5_400_000),
+            ("2days", 172_800_000),
+            ("1h, 2m", 3_720_000),
+            ("3 Secs", 3_000),
+            ("  2w ", 1_209_600_000),
+            ("PT1H30M", 5_400_000),
+            ("P1DT2H", 93_600_000),
+            ("P2W", 1_209_600_000),
+            ("PT0.5S", 500),
+            ("pt90s", 90_000),
+        ];
+        for (text, millis) in cases {
+            assert_eq!(parse(text), Ok(Duration::from_millis(millis)), "{text}");
+        }
+        assert_eq!(parse("1us 2ns"), Ok(Duration::from_nanos(1_002)));
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        use DurationParseError::*;
+        let cases = [
+            ("", Empty),
+            ("   ", Empty),
+            ("10m30", MissingUnit(6)),
+            ("10 m 5", MissingUnit(7)),
+            ("5x", UnknownUnit(2, String::from("x"))),
+            ("1h 5 fortnights", UnknownUnit(6, String::from("fortnights"))),
+            ("-5m", ExpectedNumber(1)),
+            ("1h and", ExpectedNumber(7)),
+            ("P", ExpectedNumber(2)),
+            ("PT", ExpectedNumber(3)),
+            ("P1DT", ExpectedNumber(5)),
+            ("P1M", Iso(3, "months have no fixed length")),
+            ("P1Y", Iso(3, "years have no fixed length")),
+            ("PT1M1H", Iso(6, "designator out of order")),
+            ("PT1H1", MissingUnit(6)),
+            ("PT1.5H30M", Iso(7, "only the last number can have a fraction")),
+            ("P1DTT1H", Iso(5, "T given twice")),
+        ];
+        for (text, error) in cases {
+            assert_eq!(parse(text), Err(error), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn too_long_points_at_the_component() {
+        assert_eq!(parse("99999999999999999999999d"), Err(DurationParseError::TooLong(1)));
+        assert_eq!(parse("1d 300000000000000w"), Err(DurationParseError::TooLong(4)));
+        assert_eq!(parse("1h 213503982334602d"), Err(DurationParseError::TooLong(4)));
+        assert_eq!(parse("PT1H99999999999999999999S"), Err(DurationParseError::TooLong(5)));
+        let max = DurationWrapper(Duration::MAX).to_string();
+        assert_eq!(parse(&max), Ok(Duration::MAX));
+        assert!(matches!(parse(&format!("{max} 1ns")), Err(DurationParseError::TooLong(_))));
+    }
+
+    #[test]
+    fn displays_normalized() {
+        let cases = [
+            (Duration::ZERO, "0s"),
+            (Duration::from_secs(7_500), "2h5m"),
+            (Duration::from_secs(90_000), "1d1h"),
+            (Duration::from_millis(1_500), "1s500ms"),
+            (Duration::from_nanos(1_001), "1us1ns"),
+        ];
+        for (duration, text) in cases {
+            assert_eq!(DurationWrapper(duration).to_string(), text);
+        }
+    }
+
+    fn durations() -> impl Strategy<Value = Duration> {
+        prop_oneof![
+            any::<u64>().prop_map(Duration::from_nanos),
+            (0..=u64::MAX / 2, 0..1_000_000_000u32).prop_map(|(secs, nanos)| Duration::new(secs, nanos)),
+            (0..10_000_000u64).prop_map(Duration::from_secs),
+            (0..u64::MAX, 0..1_000_000_000u32).prop_map(|(secs, nanos)| Duration::new(secs, nanos)),
+        ]
+    }
+
+    // A duration written as a run of components in any of the units and spellings
+    fn written() -> impl Strategy<Value = String> {
+        let unit = prop::sample::select(UNITS.iter().flat_map(|(_, names)| names.iter().copied()).collect::<Vec<_>>());
+        let separator = prop::sample::select(vec!["", " ", ", ", " and "]);
+        prop::collection::vec((0..100_000u64, " ?", unit, separator), 1..6).prop_map(|components| {
+            let mut text = String::new();
+            for (i, (value, space, unit, separator)) in components.iter().enumerate() {
+                if i > 0 {
+                    text += if separator.is_empty() { " " } else { separator };
+                }
+                text += &format!("{value}{space}{unit}");
+            }
+            text
+        })
+    }
+
+    fn local(text: &str) -> DateTime<Local> {
+        chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap()
+    }
+
+    #[test]
+    fn schedules_take_intervals_in_words() {
+        let hourly = Schedule::Every {
+            interval: Duration::from_secs(3600),
+            days: Weekdays::ALL,
+        };
+        assert_eq!("every 1 hour".parse::<Schedule>().unwrap(), hourly);
+        assert_eq!("1 hour".parse::<Schedule>().unwrap(), hourly);
+        assert_eq!(
+            "Mon-Fri every 1 hour 30 minutes".parse::<Schedule>().unwrap(),
+            Schedule::Every {
+                interval: Duration::from_secs(5400),
+                days: "Mon-Fri".parse().unwrap(),
+            }
+        );
+        assert_eq!("Sat,Sun 07:30, 12:00".parse::<Schedule>().unwrap().to_string(), "Sat-Sun 07:30,12:00");
+        assert!(matches!("Funday 10:00".parse::<Schedule>(), Err(ScheduleParseError::Weekdays(_))));
+        assert!(matches!("every 10x".parse::<Schedule>(), Err(ScheduleParseError::Interval(_, Some(_)))));
+        assert!(matches!("0s".parse::<Schedule>(), Err(ScheduleParseError::Interval(_, None))));
+        assert!(matches!("61 * * * *".parse::<Schedule>(), Err(ScheduleParseError::Cron(_))));
+    }
+
+    #[test]
+    fn schedules_display_as_they_parse() {
+        for text in ["never", "10m", "Mon-Fri 1h30m", "07:00,17:30:15", "Fri-Mon 08:00", "*/10 9-17 * * Mon-Fri", "10m; 0 12 * * Sun"] {
+            let schedule: Schedule = text.parse().unwrap();
+            assert_eq!(schedule.to_string(), text);
+            assert_eq!(schedule.to_string().parse::<Schedule>().unwrap(), schedule);
+        }
+    }
+
+    #[test]
+    fn cron_treats_full_fields_as_unrestricted() {
+        // 2026-10-20 is a Tuesday, so only the day of week limits these to the next Monday
+        let after = local("2026-10-20 13:00");
+        let monday = Some(local("2026-10-26 12:00"));
+        for text in ["0 12 * * Mon", "0 12 */1 * Mon", "0 12 1-31 * Mon", "0 12 * * 1"] {
+            assert_eq!(text.parse::<Schedule>().unwrap().next(after), monday, "{text}");
+        }
+        // Both restricted, either one will do
+        assert_eq!("0 12 21 * Mon".parse::<Schedule>().unwrap().next(after), Some(local("2026-10-21 12:00")));
+        // A full day of week leaves the day of month to decide
+        assert_eq!("0 12 25 * 0-6".parse::<Schedule>().unwrap().next(after), Some(local("2026-10-25 12:00")));
+    }
+
+    #[test]
+    fn schedules_past_the_end_of_time_have_no_next() {
+        let schedule = Schedule::from(Duration::MAX);
+        assert_eq!(schedule.next(Local::now()), None);
+        assert!(!schedule.due(Local::now(), Local::now()));
+    }
+
+    #[test]
+    fn schedules_load_from_older_configs() {
+        let at: Schedule = ron::from_str(r#"["07:00:00", "17:30:00"]"#).unwrap();
+        assert_eq!(at.to_string(), "07:00,17:30");
+        assert_eq!(ron::from_str::<Schedule>("[]").unwrap(), Schedule::Never);
+        let every: Schedule = ron::from_str("(secs: 600, nanos: 0)").unwrap();
+        assert_eq!(every.to_string(), "10m");
+    }
+
+    proptest! {
+        #[test]
+        fn display_parses_back(duration in durations()) {
+            prop_assert_eq!(parse(&DurationWrapper(duration).to_string()), Ok(duration));
+        }
+
+        #[test]
+        fn parsing_is_stable_through_display(text in written()) {
+            let duration = parse(&text).unwrap();
+            let normalized = DurationWrapper(duration).to_string();
+            prop_assert_eq!(parse(&normalized), Ok(duration));
+            prop_assert_eq!(DurationWrapper(parse(&normalized).unwrap()).to_string(), normalized);
+        }
+
+        #[test]
+        fn iso_matches_units(days in 0..1000u64, hours in 0..24u64, minutes in 0..60u64, seconds in 0..60u64) {
+            let iso = format!("P{days}DT{hours}H{minutes}M{seconds}S");
+            let units = format!("{days}d{hours}h{minutes}m{seconds}s");
+            prop_assert_eq!(parse(&iso), parse(&units));
+        }
+
+        #[test]
+        fn never_panics(text in "\\PC{0,24}") {
+            let _ = parse(&text);
+        }
+    }
 }
@@ -0,0 +1,221 @@
+use crate::history::{ForecastRecord, Observation};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+// What actually happened at a location on a given local date
+#[derive(Debug, Clone)]
+pub struct ObservedDay {
+    pub high_c: f64,
+    pub low_c: f64,
+    pub precipitated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Skill {
+    pub samples: usize,
+    pub high_abs_error: f64,
+    pub high_error: f64,
+    pub low_abs_error: f64,
+    pub low_error: f64,
+    pub hits: usize,
+    pub misses: usize,
+    pub false_alarms: usize,
+    pub correct_negatives: usize,
+}
+
+impl Skill {
+    fn add(&mut self, forecast: &ForecastRecord, observed: &ObservedDay) {
+        let high_error = forecast.maxtemp_c - observed.high_c;
+        let low_error = forecast.mintemp_c - observed.low_c;
+        self.samples += 1;
+        self.high_abs_error += high_error.abs();
+        self.high_error += high_error;
+        self.low_abs_error += low_error.abs();
+        self.low_error += low_error;
+        match (forecast.will_precip, observed.precipitated) {
+            (true, true) => self.hits += 1,
+            (false, true) => self.misses += 1,
+            (true, false) => self.false_alarms += 1,
+            (false, false) => self.correct_negatives += 1,
+        }
+    }
+
+    pub fn high_mae(&self) -> f64 {
+        self.high_abs_error / self.samples as f64
+    }
+
+    pub fn high_bias(&self) -> f64 {
+        self.high_error / self.samples as f64
+    }
+
+    pub fn low_mae(&self) -> f64 {
+        self.low_abs_error / self.samples as f64
+    }
+
+    pub fn low_bias(&self) -> f64 {
+        self.low_error / self.samples as f64
+    }
+
+    // Probability of detection, None when it never precipitated
+    pub fn hit_rate(&self) -> Option<f64> {
+        let events = self.hits + self.misses;
+        (events > 0).then(|| self.hits as f64 / events as f64)
+    }
+
+    // False alarm ratio, None when precipitation was never forecast
+    pub fn false_alarm_rate(&self) -> Option<f64> {
+        let forecasts = self.hits + self.false_alarms;
+        (forecasts > 0).then(|| self.false_alarms as f64 / forecasts as f64)
+    }
+}
+
+// Only days that have been observed from start to finish can be verified, so the
+// most recent date seen for each location is left out.
+pub fn observed_days(observations: &[Observation]) -> BTreeMap<(String, NaiveDate), ObservedDay> {
+    let mut latest: BTreeMap<&str, NaiveDate> = BTreeMap::new();
+    for observation in observations {
+        let date = observation.local_time.date();
+        let entry = latest.entry(&observation.location).or_insert(date);
+        *entry = (*entry).max(date);
+    }
+
+    let mut days: BTreeMap<(String, NaiveDate), ObservedDay> = BTreeMap::new();
+    for observation in observations {
+        let date = observation.local_time.date();
+        if latest.get(observation.location.as_str()) == Some(&date) {
+            continue;
+        }
+        days.entry((observation.location.clone(), date))
+            .and_modify(|day| {
+                day.high_c = day.high_c.max(observation.temp_c);
+                day.low_c = day.low_c.min(observation.temp_c);
+                day.precipitated |= observation.precip_mm > 0.0;
+            })
+            .or_insert(ObservedDay {
+                high_c: observation.temp_c,
+                low_c: observation.temp_c,
+                precipitated: observation.precip_mm > 0.0,
+            });
+    }
+    days
+}
+
+// Skill grouped by provider and lead time in days
+pub fn verify(
+    forecasts: &[ForecastRecord],
+    observations: &[Observation],
+) -> BTreeMap<(String, i64), Skill> {
+    let days = observed_days(observations);
+
+    // The daemon records every forecast it fetches, many a day. Only the first one
+    // issued for each date and lead time is scored so polling more often doesn't
+    // count for more.
+    let mut first: BTreeMap<(&str, &str, NaiveDate, i64), &ForecastRecord> = BTreeMap::new();
+    for forecast in forecasts {
+        let key = (forecast.provider.as_str(), forecast.location.as_str(), forecast.date, forecast.lead_days);
+        first
+            .entry(key)
+            .and_modify(|kept| {
+                if forecast.issued < kept.issued {
+                    *kept = forecast;
+                }
+            })
+            .or_insert(forecast);
+    }
+
+    let mut skill: BTreeMap<(String, i64), Skill> = BTreeMap::new();
+    for forecast in first.into_values() {
+        if let Some(observed) = days.get(&(forecast.location.clone(), forecast.date)) {
+            skill
+                .entry((forecast.provider.clone(), forecast.lead_days))
+                .or_default()
+                .add(forecast, observed);
+        }
+    }
+    skill
+}
+
+pub fn print_report(skill: &BTreeMap<(String, i64), Skill>) {
+    if skill.is_empty() {
+        println!("No forecasts could be matched with observations yet. Leave the daemon running for a few days and try again.");
+        return;
+    }
+
+    let rate = |value: Option<f64>| match value {
+        Some(value) => format!("{:.0}%", value * 100.0),
+        None => String::from("-"),
+    };
+
+    println!("{:=^106}", "Forecast Verification");
+    println!(
+        "{:<12}{:>6}{:>9}{:>10}{:>10}{:>10}{:>10}{:>7}{:>8}{:>8}{:>8}{:>8}",
+        "Provider", "Lead", "Samples", "High MAE", "High Bias", "Low MAE", "Low Bias",
+        "Hits", "Misses", "F.Alarm", "POD", "FAR"
+    );
+    for ((provider, lead_days), skill) in skill {
+        println!(
+            "{:<12}{:>5}d{:>9}{:>9.1}°{:>+9.1}°{:>9.1}°{:>+9.1}°{:>7}{:>8}{:>8}{:>8}{:>8}",
+            provider,
+            lead_days,
+            skill.samples,
+            skill.high_mae(),
+            skill.high_bias(),
+            skill.low_mae(),
+            skill.low_bias(),
+            skill.hits,
+            skill.misses,
+            skill.false_alarms,
+            rate(skill.hit_rate()),
+            rate(skill.false_alarm_rate()),
+        );
+    }
+    println!("Temperatures in °C. Bias is forecast minus observed.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forecast(issued: i64, maxtemp_c: f64) -> ForecastRecord {
+        ForecastRecord {
+            provider: String::from("test"),
+            location: String::from("here"),
+            issued,
+            date: NaiveDate::from_ymd_opt(2026, 3, 2).unwrap(),
+            lead_days: 1,
+            maxtemp_c,
+            mintemp_c: 0.0,
+            avgtemp_c: 5.0,
+            totalprecip_mm: 0.0,
+            chance_of_precip: 0.0,
+            will_precip: false,
+        }
+    }
+
+    fn observation(day: u32, temp_c: f64) -> Observation {
+        let local_time = NaiveDate::from_ymd_opt(2026, 3, day).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        Observation {
+            provider: String::from("test"),
+            location: String::from("here"),
+            time: local_time.and_utc().timestamp(),
+            local_time,
+            temp_c,
+            feelslike_c: temp_c,
+            humidity: None,
+            pressure_mb: None,
+            precip_mm: 0.0,
+            wind_kph: None,
+            condition: String::from("Clear"),
+        }
+    }
+
+    #[test]
+    fn only_the_first_issue_is_scored() {
+        let forecasts = [forecast(300, 14.0), forecast(100, 12.0), forecast(200, 16.0)];
+        let observations = [observation(2, 10.0), observation(2, 0.0), observation(3, 5.0)];
+        let skill = verify(&forecasts, &observations);
+        let skill = &skill[&(String::from("test"), 1)];
+        assert_eq!(skill.samples, 1);
+        assert_eq!(skill.high_bias(), 2.0);
+    }
+}